@@ -1,6 +1,7 @@
 import { filter, Subject } from 'rxjs'
 import { Destructible } from './helpers'
 import * as russh from './native'
+import type { KeyPair } from './index'
 
 export type AgentConnectionSpec = {
     kind: 'pageant',
@@ -23,6 +24,65 @@ export function makeRusshAgentConnection (spec: AgentConnectionSpec): russh.Agen
     )
 }
 
+/** Signs `data` like `ssh-keygen -Y sign` with a key held by the agent */
+export async function signSshsigWithAgent (
+    spec: AgentConnectionSpec,
+    key: russh.SshPublicKey,
+    namespace: string,
+    data: Uint8Array,
+    hash?: russh.SshSigHash,
+): Promise<string> {
+    return russh.signSshsigWithAgent(makeRusshAgentConnection(spec), key, namespace, data, hash)
+}
+
+/** Manages the keys held by an agent, in place of `ssh-add` */
+export class SSHAgentClient {
+    private inner: russh.SshAgentClient
+
+    constructor (spec: AgentConnectionSpec) {
+        this.inner = new russh.SshAgentClient(makeRusshAgentConnection(spec))
+    }
+
+    /** The agent's keys, with their comments. Empty while the agent is locked */
+    async listIdentities (): Promise<russh.SshPublicKey[]> {
+        return this.inner.listIdentities()
+    }
+
+    /**
+     * `comment` defaults to the key's own. The agent forgets the key after `lifetime` seconds,
+     * and asks before each use with `confirm`, as with `ssh-add -t` and `ssh-add -c`
+     */
+    async addIdentity (keyPair: KeyPair, comment?: string, lifetime?: number, confirm?: boolean): Promise<void> {
+        await this.inner.addIdentity(keyPair['inner'], comment, lifetime, confirm)
+    }
+
+    async removeIdentity (key: russh.SshPublicKey): Promise<void> {
+        await this.inner.removeIdentity(key)
+    }
+
+    async removeAll (): Promise<void> {
+        await this.inner.removeAll()
+    }
+
+    /** A `Uint8Array` passphrase is zero-filled once read */
+    async lock (passphrase: string | Uint8Array): Promise<void> {
+        await this.inner.lock(passphrase)
+    }
+
+    /** A `Uint8Array` passphrase is zero-filled once read */
+    async unlock (passphrase: string | Uint8Array): Promise<void> {
+        await this.inner.unlock(passphrase)
+    }
+
+    /**
+     * Returns the SSH signature blob. Pass `AGENT_RSA_SHA2_256` or `AGENT_RSA_SHA2_512` in
+     * `flags` to get an SHA-2 signature from an RSA key
+     */
+    async sign (key: russh.SshPublicKey, data: Uint8Array, flags?: number): Promise<Uint8Array> {
+        return this.inner.sign(key, data, flags)
+    }
+}
+
 export class SSHAgentStream extends Destructible {
     data$ = this.data.asObservable().pipe(filter(data => data.length > 0))
 
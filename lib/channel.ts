@@ -17,11 +17,20 @@ export interface X11Options {
     screenNumber: number,
 }
 
+export interface ExitSignal {
+    signal: string,
+    coreDumped: boolean,
+    errorMessage: string,
+    languageTag: string,
+}
+
 export class Channel extends Destructible {
     readonly data$: Observable<Uint8Array>
     readonly extendedData$: Observable<[number, Uint8Array]>
     readonly eof$: Observable<void>
     readonly closed$: Observable<void>
+    readonly exitStatus$: Observable<number>
+    readonly exitSignal$: Observable<ExitSignal>
 
     constructor(
         public readonly id: number,
@@ -33,6 +42,13 @@ export class Channel extends Destructible {
         this.extendedData$ = events.extendedData$.pipe(filter(([channel]) => channel === id), map(([_, ext, data]) => [ext, data]))
         this.eof$ = events.eof$.pipe(filter(channel => channel === id), map(() => { }))
         this.closed$ = events.close$.pipe(filter(channel => channel === id), map(() => { }))
+        this.exitStatus$ = events.exitStatus$.pipe(filter(([channel]) => channel === id), map(([_, status]) => status))
+        this.exitSignal$ = events.exitSignal$.pipe(filter(([channel]) => channel === id), map(([_, signal, coreDumped, errorMessage, languageTag]) => ({
+            signal,
+            coreDumped,
+            errorMessage,
+            languageTag,
+        })))
     }
 
     async take(): Promise<russh.SshChannel> {
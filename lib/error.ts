@@ -0,0 +1,85 @@
+export type RusshErrorCategory = 'transport' | 'proxy' | 'kex' | 'auth' | 'channel' | 'sftp' | 'agent' | 'key' | 'argument'
+
+interface RusshErrorDetails {
+    code: string
+    category: RusshErrorCategory
+    ioKind: string | null
+    sftpStatus: number | null
+    message: string
+}
+
+export class RusshError extends Error {
+    /** Stable identifier, e.g. `ERR_HOST_KEY_REJECTED` or `ERR_SFTP_NO_SUCH_FILE` */
+    readonly code: string
+    readonly category: RusshErrorCategory
+    /** Name of the underlying Rust `std::io::ErrorKind`, e.g. `ConnectionRefused` */
+    readonly ioKind: string | null
+    /** `SSH_FX_*` status code returned by the SFTP server */
+    readonly sftpStatus: number | null
+
+    constructor (details: RusshErrorDetails) {
+        super(details.message)
+        this.name = 'RusshError'
+        this.code = details.code
+        this.category = details.category
+        this.ioKind = details.ioKind
+        this.sftpStatus = details.sftpStatus
+    }
+
+    /** Unpacks the JSON details the native module puts in error messages */
+    static fromNative (error: unknown): unknown {
+        if (!(error instanceof Error) || !error.message.startsWith('{')) {
+            return error
+        }
+        try {
+            const details = JSON.parse(error.message)
+            if (typeof details.code !== 'string' || typeof details.category !== 'string') {
+                return error
+            }
+            const result = new RusshError(details)
+            result.stack = error.stack?.replace(error.message, details.message)
+            return result
+        } catch {
+            return error
+        }
+    }
+}
+
+function wrapFunction (fn: (...args: any[]) => any): (...args: any[]) => any {
+    return function (this: unknown, ...args: any[]) {
+        let result
+        try {
+            result = fn.apply(this, args)
+        } catch (error) {
+            throw RusshError.fromNative(error)
+        }
+        if (result instanceof Promise) {
+            return result.catch(error => {
+                throw RusshError.fromNative(error)
+            })
+        }
+        return result
+    }
+}
+
+/** Makes every function and class method of the native module throw `RusshError`s */
+export function wrapNativeErrors (native: Record<string, any>): void {
+    for (const [name, value] of Object.entries(native)) {
+        if (typeof value !== 'function') {
+            continue
+        }
+        if (value.prototype) {
+            for (const target of [value, value.prototype]) {
+                for (const key of Object.getOwnPropertyNames(target)) {
+                    const descriptor = Object.getOwnPropertyDescriptor(target, key)
+                    if (key !== 'constructor' && typeof descriptor?.value === 'function' && descriptor.writable) {
+                        target[key] = wrapFunction(descriptor.value)
+                    }
+                }
+            }
+        }
+        if (!/^[A-Z]/.test(name)) {
+            native[name] = wrapFunction(value)
+        }
+    }
+}
@@ -1,12 +1,15 @@
 import * as russh from './native'
 import { AsyncSubject, Subject } from "rxjs"
+import { RusshError } from './error'
 
 export class ClientEventInterface {
     data$ = new Subject<[number, Uint8Array]>()
     extendedData$ = new Subject<[number, number, Uint8Array]>()
     eof$ = new Subject<number>()
     close$ = new Subject<number>()
-    disconnect$ = new Subject<void>()
+    exitStatus$ = new Subject<[number, number]>()
+    exitSignal$ = new Subject<[number, string, boolean, string, string]>()
+    disconnect$ = new Subject<russh.DisconnectEvent>()
     x11ChannelOpen$ = new Subject<[russh.SshChannel, string, number]>()
     tcpChannelOpen$ = new Subject<[russh.SshChannel, string, number, string, number]>()
     agentChannelOpen$ = new Subject<[russh.SshChannel]>()
@@ -17,6 +20,8 @@ export class ClientEventInterface {
         this.extendedData$.complete()
         this.eof$.complete()
         this.close$.complete()
+        this.exitStatus$.complete()
+        this.exitSignal$.complete()
         this.disconnect$.complete()
         this.x11ChannelOpen$.complete()
         this.tcpChannelOpen$.complete()
@@ -40,8 +45,19 @@ export class ClientEventInterface {
         this.close$.next(channel)
     }
 
-    disconnectCallback = () => {
-        this.disconnect$.next()
+    exitStatusCallback = (_: unknown, channel: number, status: number) => {
+        this.exitStatus$.next([channel, status])
+    }
+
+    exitSignalCallback = (_: unknown, channel: number, signal: string, coreDumped: boolean, errorMessage: string, languageTag: string) => {
+        this.exitSignal$.next([channel, signal, coreDumped, errorMessage, languageTag])
+    }
+
+    disconnectCallback = (_: unknown, event: russh.DisconnectEvent) => {
+        this.disconnect$.next({
+            ...event,
+            error: event.error && RusshError.fromNative(event.error) as Error,
+        })
     }
 
     x11ChannelOpenCallback = (_: unknown, channel: russh.SshChannel, address: string, port: number) => {
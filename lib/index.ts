@@ -4,15 +4,60 @@ import { SFTP } from './sftp'
 import { Channel } from './channel'
 import { ClientEventInterface } from './events'
 
-import russh, { SshKeyPair, KeyboardInteractiveAuthenticationPrompt, SshClient, SshChannel, SshPublicKey, SshTransport } from './native'
+import russh, { ConnectOptions, SshCertificate, ConnectionInfo, DisconnectEvent, DisconnectReasonCode, RsaSignatureHash, KeyType, PrivateKeyExportOptions, SshKeyPair, SshSigHash, KeyboardInteractiveAuthenticationPrompt, KeyboardInteractiveRule, SshClient, SshChannel, SshPublicKey, SshTransport } from './native'
 import { AgentConnectionSpec, makeRusshAgentConnection } from './agent'
 
 export class KeyPair {
     private constructor(protected inner: SshKeyPair) { }
 
-    static async parse(data: string, passphrase?: string): Promise<KeyPair> {
+    /** `Uint8Array` arguments are zero-filled once read */
+    static async parse(data: string | Uint8Array, passphrase?: string | Uint8Array): Promise<KeyPair> {
         return new KeyPair(await russh.parseKey(data, passphrase))
     }
+
+    /**
+     * Reads a key file, expanding `~`. `passphrase` is only called for encrypted keys, and again
+     * after a wrong passphrase, up to `maxAttempts` times. Resolve to `null` to give up
+     */
+    static async load(
+        path: string,
+        passphrase?: (path: string, attempt: number) => Promise<string | Uint8Array | null>,
+        maxAttempts?: number,
+    ): Promise<KeyPair> {
+        return new KeyPair(await russh.loadKeyFile(
+            path,
+            passphrase ? (_, path, attempt) => passphrase(path, attempt) : undefined,
+            maxAttempts,
+        ))
+    }
+
+    /** `bits` is the RSA modulus size or the ECDSA curve size, as with `ssh-keygen -b` */
+    static async generate(type: KeyType, bits?: number, comment?: string): Promise<KeyPair> {
+        return new KeyPair(await russh.generateKey(type, bits, comment))
+    }
+
+    get comment(): string {
+        return this.inner.comment()
+    }
+
+    /** Serializes the key, by default to the OpenSSH format. Pass a `passphrase` to encrypt it */
+    async export(options?: PrivateKeyExportOptions): Promise<string> {
+        return this.inner.export(options)
+    }
+
+    /** Signs `data` like `ssh-keygen -Y sign -n <namespace>` and returns the armored signature */
+    signSshsig(namespace: string, data: Uint8Array, hash?: SshSigHash): string {
+        return this.inner.signSshsig(namespace, data, hash)
+    }
+
+    /** Wipes the private key from memory. The key pair can't be used afterwards */
+    dispose(): void {
+        this.inner.dispose()
+    }
+
+    get disposed(): boolean {
+        return this.inner.isDisposed()
+    }
 }
 
 export interface X11ChannelOpenEvent {
@@ -39,7 +84,7 @@ export type KeyboardInteractiveAuthenticationState = {
     prompts: () => KeyboardInteractiveAuthenticationPrompt[]
 }
 
-export interface Config {
+export interface Config extends Omit<ConnectOptions, 'cipherAlgos' | 'kexAlgos' | 'keyAlgos' | 'macAlgos' | 'compressionAlgos' | 'keepaliveMax'> {
     preferred?: {
         ciphers?: string[]
         kex?: string[],
@@ -47,11 +92,27 @@ export interface Config {
         mac?: string[],
         compression?: string[],
     },
-    connectionTimeoutSeconds?: number,
-    keepaliveIntervalSeconds?: number,
     keepaliveCountMax?: number,
 }
 
+export interface KeyboardInteractiveAuthenticationOptions {
+    /** Comma-separated submethod hints for the server, e.g. `pam` */
+    submethods?: string
+    /** Answer matching prompts without asking, e.g. with a TOTP code */
+    rules?: KeyboardInteractiveRule[]
+}
+
+export interface AgentAuthenticationOptions {
+    /** Only offer these keys, in this order: SHA256 fingerprints, base64 key blobs or OpenSSH public key lines */
+    identities?: string[]
+    /** Receives the candidate keys and returns the indices of the keys to try, in order */
+    filter?: (keys: SshPublicKey[]) => Promise<number[]>
+    /** Stop after this many keys, e.g. to stay under the server's `MaxAuthTries` */
+    maxAttempts?: number
+    /** Signature algorithm to use for RSA keys, `rsa-sha2-256` by default */
+    rsaHash?: RsaSignatureHash
+}
+
 export class SSHClient extends Destructible {
     readonly disconnect$ = this.events.disconnect$.asObservable()
     readonly banner$ = this.events.banner$.asObservable()
@@ -61,32 +122,43 @@ export class SSHClient extends Destructible {
         private events: ClientEventInterface,
     ) { super() }
 
+    /**
+     * `serverKeyCallback` always gets the server's plain host key. Host
+     * certificates aren't negotiated, so `KnownHosts.checkCertificate()`
+     * can't be used on a live connection.
+     */
     static async connect(
         transport: SshTransport,
         serverKeyCallback: (key: SshPublicKey) => Promise<boolean>,
         config?: Config,
     ): Promise<SSHClient> {
         const eventInterface = new ClientEventInterface()
+        const { preferred, keepaliveCountMax, ...options } = config ?? {}
         const russhClient = await russh.connect(
             transport,
-            config?.preferred?.ciphers,
-            config?.preferred?.kex,
-            config?.preferred?.key,
-            config?.preferred?.mac,
-            config?.preferred?.compression,
-            config?.connectionTimeoutSeconds,
-            config?.keepaliveIntervalSeconds,
-            config?.keepaliveCountMax ?? 3,
-            (_, k) => serverKeyCallback(k),
-            eventInterface.dataCallback,
-            eventInterface.extendedDataCallback,
-            eventInterface.eofCallback,
-            eventInterface.closeCallback,
-            eventInterface.disconnectCallback,
-            eventInterface.x11ChannelOpenCallback,
-            eventInterface.tcpChannelOpenCallback,
-            eventInterface.agentChannelOpenCallback,
-            eventInterface.bannerCallback,
+            {
+                ...options,
+                cipherAlgos: preferred?.ciphers,
+                kexAlgos: preferred?.kex,
+                keyAlgos: preferred?.key,
+                macAlgos: preferred?.mac,
+                compressionAlgos: preferred?.compression,
+                keepaliveMax: keepaliveCountMax,
+            },
+            {
+                serverKeyCallback: (_, k) => serverKeyCallback(k),
+                dataCallback: eventInterface.dataCallback,
+                extendedDataCallback: eventInterface.extendedDataCallback,
+                eofCallback: eventInterface.eofCallback,
+                closeCallback: eventInterface.closeCallback,
+                exitStatusCallback: eventInterface.exitStatusCallback,
+                exitSignalCallback: eventInterface.exitSignalCallback,
+                disconnectCallback: eventInterface.disconnectCallback,
+                x11ChannelOpenCallback: eventInterface.x11ChannelOpenCallback,
+                tcpipChannelOpenCallback: eventInterface.tcpChannelOpenCallback,
+                agentChannelOpenCallback: eventInterface.agentChannelOpenCallback,
+                bannerCallback: eventInterface.bannerCallback,
+            },
         )
 
         eventInterface.disconnect$.subscribe(() => {
@@ -100,7 +172,31 @@ export class SSHClient extends Destructible {
         super.destruct()
     }
 
-    async authenticateWithPassword(username: string, password: string): Promise<AuthenticatedSSHClient | null> {
+    connectionInfo(): ConnectionInfo | null {
+        return this.client.connectionInfo()
+    }
+
+    /**
+     * Like the other `authenticateWith*` methods, only tells whether the
+     * login is complete. The server's remaining methods and partial success
+     * aren't available, so with `AuthenticationMethods` try each required
+     * method in turn.
+     */
+    async authenticateWithNone(username: string): Promise<AuthenticatedSSHClient | null> {
+        this.assertNotDestructed()
+        const result = await this.client.authenticateNone(username)
+        if (result) {
+            return this.intoAuthenticated()
+        }
+        return null
+    }
+
+    /**
+     * Expired passwords can't be changed here: a server that answers with a
+     * password change request is only noticed when it gives up on the login.
+     * A `Uint8Array` password is zero-filled once read.
+     */
+    async authenticateWithPassword(username: string, password: string | Uint8Array): Promise<AuthenticatedSSHClient | null> {
         this.assertNotDestructed()
         const result = await this.client.authenticatePassword(username, password)
         if (result) {
@@ -109,21 +205,79 @@ export class SSHClient extends Destructible {
         return null
     }
 
-    async authenticateWithKeyPair(username: string, keyPair: KeyPair): Promise<AuthenticatedSSHClient | null> {
+    /**
+     * `rsaHash` overrides the signature algorithm used for RSA keys. It is
+     * `rsa-sha2-256` by default, as the server's `server-sig-algs` isn't
+     * consulted.
+     */
+    async authenticateWithKeyPair(
+        username: string,
+        keyPair: KeyPair,
+        rsaHash?: RsaSignatureHash,
+    ): Promise<AuthenticatedSSHClient | null> {
         this.assertNotDestructed()
-        const result = await this.client.authenticatePublickey(username, keyPair['inner'])
+        const result = await this.client.authenticatePublickey(username, keyPair['inner'], rsaHash)
         if (result) {
             return this.intoAuthenticated()
         }
         return null
     }
 
-    async startKeyboardInteractiveAuthentication(username: string): Promise<KeyboardInteractiveAuthenticationState> {
+    async authenticateWithCertificate(
+        username: string,
+        keyPair: KeyPair,
+        certificate: SshCertificate,
+    ): Promise<AuthenticatedSSHClient | null> {
         this.assertNotDestructed()
-        return await this.client.startKeyboardInteractiveAuthentication(username) as unknown as KeyboardInteractiveAuthenticationState
+        const result = await this.client.authenticateCertificate(username, keyPair['inner'], certificate)
+        if (result) {
+            return this.intoAuthenticated()
+        }
+        return null
     }
 
-    async continueKeyboardInteractiveAuthentication(responses: string[]): Promise<AuthenticatedSSHClient | KeyboardInteractiveAuthenticationState> {
+    /**
+     * Public key authentication where `sign` produces the signature, e.g. through
+     * an OS keychain or a KMS. It must resolve to an SSH signature blob
+     * (`string algorithm, string signature`) over `data`.
+     */
+    async authenticateWithSigner(
+        username: string,
+        publicKey: SshPublicKey,
+        sign: (data: Uint8Array, algorithm: string) => Promise<Uint8Array>,
+        rsaHash?: RsaSignatureHash,
+    ): Promise<AuthenticatedSSHClient | null> {
+        this.assertNotDestructed()
+        const result = await this.client.authenticateSigner(username, publicKey, (_, data, algorithm) => sign(data, algorithm), rsaHash)
+        if (result) {
+            return this.intoAuthenticated()
+        }
+        return null
+    }
+
+    /**
+     * Prompts matching one of `options.rules` are answered automatically, and
+     * only the rest are returned. If the rules cover every prompt, this
+     * resolves straight to the authenticated client.
+     */
+    async startKeyboardInteractiveAuthentication(
+        username: string,
+        options?: KeyboardInteractiveAuthenticationOptions,
+    ): Promise<AuthenticatedSSHClient | KeyboardInteractiveAuthenticationState> {
+        this.assertNotDestructed()
+        const result = await this.client.startKeyboardInteractiveAuthentication(
+            username,
+            options?.submethods,
+            options?.rules,
+        )
+        if (result.state === 'success') {
+            return this.intoAuthenticated()
+        }
+        return result as unknown as KeyboardInteractiveAuthenticationState
+    }
+
+    /** `Uint8Array` responses are zero-filled once read */
+    async continueKeyboardInteractiveAuthentication(responses: (string | Uint8Array)[]): Promise<AuthenticatedSSHClient | KeyboardInteractiveAuthenticationState> {
         this.assertNotDestructed()
         const result = await this.client.respondToKeyboardInteractiveAuthentication(responses)
         if (result.state === 'success') {
@@ -132,34 +286,47 @@ export class SSHClient extends Destructible {
         return result as unknown as KeyboardInteractiveAuthenticationState
     }
 
+    /**
+     * Only the agent's plain keys are tried. Certificates held by the agent
+     * are skipped; use `authenticateWithCertificate` with a local key for
+     * those.
+     */
     async authenticateWithAgent(
         username: string,
         connection: AgentConnectionSpec,
+        options?: AgentAuthenticationOptions,
     ): Promise<AuthenticatedSSHClient | null> {
         this.assertNotDestructed()
+        const filter = options?.filter
         const result = await this.client.authenticateAgent(
             username,
             makeRusshAgentConnection(connection),
+            {
+                identities: options?.identities,
+                maxAttempts: options?.maxAttempts,
+                rsaHash: options?.rsaHash,
+                filterCallback: filter && ((_, keys) => filter(keys)),
+            },
         )
-        if (result) {
-            return this.intoAuthenticated()
+        if (result.success) {
+            return this.intoAuthenticated(result.key ?? null)
         }
         return null
     }
 
-    async disconnect(): Promise<void> {
+    async disconnect(reason?: DisconnectReasonCode, description?: string): Promise<void> {
         this.destruct()
-        await this.client.disconnect()
+        await this.client.disconnect(reason, description)
     }
 
-    private intoAuthenticated(): AuthenticatedSSHClient {
+    private intoAuthenticated(authenticatedKey: SshPublicKey | null = null): AuthenticatedSSHClient {
         this.destruct()
-        return new AuthenticatedSSHClient(this.client, this.events)
+        return new AuthenticatedSSHClient(this.client, this.events, authenticatedKey)
     }
 }
 
 export class AuthenticatedSSHClient extends Destructible {
-    readonly disconnect$: Observable<void> = this.events.disconnect$
+    readonly disconnect$: Observable<DisconnectEvent> = this.events.disconnect$
     readonly x11ChannelOpen$: Observable<X11ChannelOpenEvent> =
         this.events.x11ChannelOpen$.pipe(mergeMap(([ch, address, port]) =>
             from(this.wrapChannel(ch).then(channel => ({
@@ -189,8 +356,14 @@ export class AuthenticatedSSHClient extends Destructible {
     constructor(
         private client: SshClient,
         private events: ClientEventInterface,
+        /** The agent identity the server accepted, if authenticated through an agent */
+        readonly authenticatedKey: SshPublicKey | null = null,
     ) { super() }
 
+    connectionInfo(): ConnectionInfo | null {
+        return this.client.connectionInfo()
+    }
+
     async openSessionChannel(): Promise<Channel> {
         return await this.wrapChannel(await this.client.channelOpenSession())
     }
@@ -233,9 +406,9 @@ export class AuthenticatedSSHClient extends Destructible {
         )
     }
 
-    async disconnect(): Promise<void> {
+    async disconnect(reason?: DisconnectReasonCode, description?: string): Promise<void> {
         this.destruct()
-        await this.client.disconnect()
+        await this.client.disconnect(reason, description)
     }
 
     private async wrapChannel(channel: SshChannel): Promise<Channel> {
@@ -246,6 +419,7 @@ export class AuthenticatedSSHClient extends Destructible {
 
 export {
     KeyboardInteractiveAuthenticationPrompt,
+    KeyboardInteractiveRule,
     SshPublicKey,
     SshTransport,
     SshChannel,
@@ -255,12 +429,37 @@ export {
     supportedMacs as getSupportedMACs,
     supportedCompressionAlgorithms as getSupportedCompressionAlgorithms,
     supportedKeyTypes as getSupportedKeyTypes,
+    algorithmPresets as getAlgorithmPresets,
+    algorithmPreset as getAlgorithmPreset,
+    AlgorithmPreset,
+    ConnectionInfo,
+    DisconnectEvent,
+    DisconnectInitiator,
+    DisconnectReasonCode,
+    KnownHosts,
+    KnownHostStatus,
+    AuthorizedKeys,
+    AuthorizedKey,
+    SshCertificate,
+    CertificateType,
+    KeyType,
+    FingerprintHash,
+    PrivateKeyExportOptions,
+    PrivateKeyFormat,
+    RsaSignatureHash,
+    SshSigHash,
+    SshSigVerification,
+    AGENT_RSA_SHA2_256, AGENT_RSA_SHA2_512,
     OPEN_APPEND, OPEN_CREATE, OPEN_READ, OPEN_TRUNCATE, OPEN_WRITE,
     SftpFile as SFTPFile,
     isPageantRunning,
+    keyInfo,
+    parsePublicKey,
+    verifySshsig,
 } from './native'
 export {
     SFTP, SFTPDirectoryEntry, SFTPMetadata,
 } from './sftp'
-export { AgentConnectionSpec, SSHAgentStream } from './agent'
-export { Channel }
+export { AgentConnectionSpec, SSHAgentClient, SSHAgentStream, signSshsigWithAgent } from './agent'
+export { Channel, ExitSignal }
+export { RusshError, RusshErrorCategory } from './error'
@@ -22,4 +22,6 @@ _russh = require(`../russh.${nativeModule}.node`)
 //     _russh = require('../russh.node')
 // }
 
+require('./error').wrapNativeErrors(_russh)
+
 module.exports = _russh
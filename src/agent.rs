@@ -1,13 +1,16 @@
 use std::sync::Arc;
 
-use napi::bindgen_prelude::Uint8Array;
+use napi::bindgen_prelude::{Promise, Uint8Array};
 use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
 use napi_derive::napi;
 use russh_keys::agent::client::{AgentClient, AgentStream};
+use russh_keys::key::PublicKey;
+use russh_keys::PublicKeyBase64;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::Mutex;
 
-use crate::error::WrappedError;
+use crate::error::{ErrorCategory, ErrorDetails, WrappedError};
+use crate::key::{public_key_with_rsa_hash, RsaSignatureHash, SshPublicKey};
 
 #[napi]
 pub enum AgentConnectionKind {
@@ -65,9 +68,76 @@ pub async fn get_agent_client(
     }
 }
 
+/// Controls which agent identities `authenticateAgent()` offers, and in
+/// which order, to stay under the server's `MaxAuthTries`.
+#[napi(object, object_to_js = false)]
+#[derive(Default)]
+pub struct AgentAuthOptions {
+    /// Only offer these keys, in this order. Entries may be SHA256
+    /// fingerprints, base64 key blobs or OpenSSH public key lines.
+    pub identities: Option<Vec<String>>,
+    /// Receives the candidate keys and returns the indices of the keys to
+    /// try, in order.
+    pub filter_callback: Option<ThreadsafeFunction<Vec<SshPublicKey>, Promise<Vec<u32>>>>,
+    pub max_attempts: Option<u32>,
+    /// Signature algorithm to request from the agent for RSA identities.
+    pub rsa_hash: Option<RsaSignatureHash>,
+}
+
+#[napi(object, object_from_js = false)]
+pub struct AgentAuthResult {
+    pub success: bool,
+    /// The identity the server accepted.
+    pub key: Option<SshPublicKey>,
+    pub attempts: u32,
+}
+
+fn identity_matches(identity: &str, key: &PublicKey) -> bool {
+    let identity = identity.trim();
+    if let Some(fingerprint) = identity.strip_prefix("SHA256:") {
+        return fingerprint.trim_end_matches('=') == key.fingerprint();
+    }
+    // `ssh-ed25519 AAAA... comment`
+    let base64 = identity.split_whitespace().nth(1).unwrap_or(identity);
+    base64 == key.public_key_base64() || identity == key.fingerprint()
+}
+
+impl AgentAuthOptions {
+    pub(crate) async fn select(&self, keys: Vec<PublicKey>) -> napi::Result<Vec<PublicKey>> {
+        let keys = keys
+            .into_iter()
+            .map(|key| public_key_with_rsa_hash(key, self.rsa_hash))
+            .collect::<Vec<_>>();
+        let mut keys = match &self.identities {
+            Some(identities) => identities
+                .iter()
+                .filter_map(|identity| keys.iter().find(|key| identity_matches(identity, key)))
+                .cloned()
+                .collect(),
+            None => keys,
+        };
+
+        if let Some(callback) = &self.filter_callback {
+            let candidates = keys.iter().cloned().map(SshPublicKey::from).collect();
+            let order = callback.call_async(Ok(candidates)).await?.await?;
+            keys = order
+                .into_iter()
+                .filter_map(|index| keys.get(index as usize).cloned())
+                .collect();
+        }
+
+        if let Some(max_attempts) = self.max_attempts {
+            keys.truncate(max_attempts as usize);
+        }
+        Ok(keys)
+    }
+}
+
+type AgentStreamWriter = tokio::io::WriteHalf<Box<dyn AgentStream + Send + Unpin>>;
+
 #[napi]
 pub struct SshAgentStream {
-    writer: Arc<Mutex<Option<tokio::io::WriteHalf<Box<dyn AgentStream + Send + Unpin>>>>>,
+    writer: Arc<Mutex<Option<AgentStreamWriter>>>,
 }
 
 #[napi]
@@ -77,9 +147,10 @@ impl SshAgentStream {
         let mut writer = self.writer.lock().await;
         if let Some(writer) = writer.as_mut() {
             writer.write_all(&data).await.map_err(|_| {
-                napi::Error::new(
-                    napi::Status::GenericFailure,
-                    "Failed to send data to channel",
+                ErrorDetails::new(
+                    "ERR_AGENT_FAILURE",
+                    ErrorCategory::Agent,
+                    "Failed to send data to agent",
                 )
             })?;
         }
@@ -0,0 +1,228 @@
+use napi::bindgen_prelude::Uint8Array;
+use napi_derive::napi;
+use russh_keys::encoding::{Encoding, Reader};
+use ssh_encoding::Encode;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use zeroize::Zeroizing;
+
+use crate::agent::{get_agent_client, AgentConnection};
+use crate::certificate::ssh_key_error;
+use crate::error::{ErrorCategory, ErrorDetails};
+use crate::key::{public_key_from_blob, SshKeyPair, SshPublicKey};
+use crate::secret::{take_secret, Secret};
+
+const FAILURE: u8 = 5;
+const SUCCESS: u8 = 6;
+const REQUEST_IDENTITIES: u8 = 11;
+const IDENTITIES_ANSWER: u8 = 12;
+const SIGN_REQUEST: u8 = 13;
+const SIGN_RESPONSE: u8 = 14;
+const ADD_IDENTITY: u8 = 17;
+const REMOVE_IDENTITY: u8 = 18;
+const REMOVE_ALL_IDENTITIES: u8 = 19;
+const LOCK: u8 = 22;
+const UNLOCK: u8 = 23;
+const ADD_ID_CONSTRAINED: u8 = 25;
+const CONSTRAIN_LIFETIME: u8 = 1;
+const CONSTRAIN_CONFIRM: u8 = 2;
+/// OpenSSH's `AGENT_MAX_LEN`.
+const MAX_MESSAGE_LEN: usize = 256 * 1024;
+
+/// Asks for an `rsa-sha2-256` signature from an RSA key.
+#[napi]
+pub const AGENT_RSA_SHA2_256: u32 = 2;
+
+/// Asks for an `rsa-sha2-512` signature from an RSA key.
+#[napi]
+pub const AGENT_RSA_SHA2_512: u32 = 4;
+
+fn agent_failure(message: &str) -> napi::Error {
+    ErrorDetails::new("ERR_AGENT_FAILURE", ErrorCategory::Agent, message).into()
+}
+
+fn malformed_response<E>(_: E) -> napi::Error {
+    agent_failure("Malformed agent response")
+}
+
+/// Manages the keys held by an SSH agent, like `ssh-add`. Every call makes
+/// its own connection to the agent.
+#[napi]
+pub struct SshAgentClient {
+    connection: AgentConnection,
+}
+
+impl SshAgentClient {
+    /// Sends a single message and returns the reply, which starts with its
+    /// type.
+    async fn request(&self, message: &[u8]) -> napi::Result<Vec<u8>> {
+        let io_error = |e: std::io::Error| -> napi::Error {
+            ErrorDetails::from_io(&e, ErrorCategory::Agent).into()
+        };
+        let mut stream = get_agent_client(&self.connection).await?.into_inner();
+
+        let mut packet = Zeroizing::new(Vec::with_capacity(message.len() + 4));
+        packet.extend_ssh_string(message);
+        stream.write_all(&packet).await.map_err(io_error)?;
+        stream.flush().await.map_err(io_error)?;
+
+        let len = stream.read_u32().await.map_err(io_error)? as usize;
+        if len == 0 || len > MAX_MESSAGE_LEN {
+            return Err(malformed_response(len));
+        }
+        let mut response = vec![0; len];
+        stream.read_exact(&mut response).await.map_err(io_error)?;
+        Ok(response)
+    }
+
+    /// For requests the agent only acknowledges.
+    async fn request_success(&self, message: &[u8], refused: &str) -> napi::Result<()> {
+        match self.request(message).await?.first() {
+            Some(&SUCCESS) => Ok(()),
+            Some(&FAILURE) => Err(agent_failure(refused)),
+            _ => Err(malformed_response(())),
+        }
+    }
+}
+
+#[napi]
+impl SshAgentClient {
+    #[napi(constructor)]
+    pub fn new(connection: &AgentConnection) -> Self {
+        Self {
+            connection: AgentConnection {
+                kind: connection.kind,
+                path: connection.path.clone(),
+            },
+        }
+    }
+
+    /// The agent's keys, with their comments. Keys of types this library
+    /// can't read, such as certificates, are left out. A locked agent
+    /// lists no keys.
+    #[napi]
+    pub async fn list_identities(&self) -> napi::Result<Vec<SshPublicKey>> {
+        let response = self.request(&[REQUEST_IDENTITIES]).await?;
+        if response.first() != Some(&IDENTITIES_ANSWER) {
+            return Err(agent_failure("The agent refused to list its keys"));
+        }
+        let mut reader = response.reader(1);
+        let count = reader.read_u32().map_err(malformed_response)?;
+        let mut keys = vec![];
+        for _ in 0..count {
+            let blob = reader.read_string().map_err(malformed_response)?;
+            let comment = reader.read_string().map_err(malformed_response)?;
+            if let Ok(key) = public_key_from_blob(blob) {
+                keys.push(key.with_comment(&String::from_utf8_lossy(comment)));
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Hands the private key to the agent. `comment` defaults to the key's
+    /// own. With `lifetime`, the agent forgets the key after that many
+    /// seconds; with `confirm`, it asks before each use, as with
+    /// `ssh-add -t` and `ssh-add -c`.
+    #[napi]
+    pub async fn add_identity(
+        &self,
+        key: &SshKeyPair,
+        comment: Option<String>,
+        lifetime: Option<u32>,
+        confirm: Option<bool>,
+    ) -> napi::Result<()> {
+        let confirm = confirm.unwrap_or(false);
+        let message_type = if lifetime.is_some() || confirm {
+            ADD_ID_CONSTRAINED
+        } else {
+            ADD_IDENTITY
+        };
+        let mut message = Zeroizing::new(vec![message_type]);
+        // The agent takes the key in the layout of an OpenSSH private key
+        key.to_ssh_key()?
+            .key_data()
+            .encode(&mut *message)
+            .map_err(|e| ssh_key_error(e.into()))?;
+        message.extend_ssh_string(comment.as_ref().unwrap_or(&key.comment).as_bytes());
+        if let Some(lifetime) = lifetime {
+            message.push(CONSTRAIN_LIFETIME);
+            message.extend_from_slice(&lifetime.to_be_bytes());
+        }
+        if confirm {
+            message.push(CONSTRAIN_CONFIRM);
+        }
+        self.request_success(&message, "The agent refused the key")
+            .await
+    }
+
+    #[napi]
+    pub async fn remove_identity(&self, key: &SshPublicKey) -> napi::Result<()> {
+        let mut message = vec![REMOVE_IDENTITY];
+        message.extend_ssh_string(&key.blob());
+        self.request_success(&message, "The agent doesn't hold this key")
+            .await
+    }
+
+    #[napi]
+    pub async fn remove_all(&self) -> napi::Result<()> {
+        self.request_success(
+            &[REMOVE_ALL_IDENTITIES],
+            "The agent refused to remove its keys",
+        )
+        .await
+    }
+
+    /// Makes the agent refuse to list keys or sign until unlocked with the
+    /// same passphrase. A `Uint8Array` passphrase is wiped once read.
+    #[napi]
+    pub async fn lock(&self, passphrase: Secret) -> napi::Result<()> {
+        let passphrase = take_secret(passphrase)?;
+        let mut message = Zeroizing::new(vec![LOCK]);
+        message.extend_ssh_string(passphrase.as_bytes());
+        self.request_success(
+            &message,
+            "The agent refused to lock, it may be locked already",
+        )
+        .await
+    }
+
+    /// A `Uint8Array` passphrase is wiped once read.
+    #[napi]
+    pub async fn unlock(&self, passphrase: Secret) -> napi::Result<()> {
+        let passphrase = take_secret(passphrase)?;
+        let mut message = Zeroizing::new(vec![UNLOCK]);
+        message.extend_ssh_string(passphrase.as_bytes());
+        self.request_success(
+            &message,
+            "The agent refused to unlock, the passphrase may be wrong",
+        )
+        .await
+    }
+
+    /// Signs `data` with one of the agent's keys and returns the SSH
+    /// signature blob (`string algorithm, string signature`). `flags`
+    /// combines `AGENT_RSA_SHA2_256` or `AGENT_RSA_SHA2_512`; without
+    /// either, RSA keys sign with `ssh-rsa`.
+    #[napi]
+    pub async fn sign(
+        &self,
+        key: &SshPublicKey,
+        data: Uint8Array,
+        flags: Option<u32>,
+    ) -> napi::Result<Uint8Array> {
+        let mut message = vec![SIGN_REQUEST];
+        message.extend_ssh_string(&key.blob());
+        message.extend_ssh_string(&data);
+        message.extend_from_slice(&flags.unwrap_or(0).to_be_bytes());
+        let response = self.request(&message).await?;
+        match response.first() {
+            Some(&SIGN_RESPONSE) => (),
+            Some(&FAILURE) => return Err(agent_failure("The agent refused to sign the data")),
+            _ => return Err(malformed_response(())),
+        }
+        let signature = response
+            .reader(1)
+            .read_string()
+            .map_err(malformed_response)?;
+        Ok(signature.to_vec().into())
+    }
+}
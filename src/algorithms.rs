@@ -0,0 +1,283 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use napi_derive::napi;
+
+#[napi]
+pub fn supported_ciphers() -> Vec<String> {
+    russh::cipher::ALL_CIPHERS
+        .iter()
+        .map(|x| x.as_ref().to_string())
+        .collect()
+}
+#[napi]
+pub fn supported_kex_algorithms() -> Vec<String> {
+    russh::kex::ALL_KEX_ALGORITHMS
+        .iter()
+        .map(|x| x.as_ref().to_string())
+        .collect()
+}
+
+#[napi]
+pub fn supported_macs() -> Vec<String> {
+    russh::mac::ALL_MAC_ALGORITHMS
+        .iter()
+        .map(|x| x.as_ref().to_string())
+        .collect()
+}
+
+#[napi]
+pub fn supported_compression_algorithms() -> Vec<String> {
+    russh::compression::ALL_COMPRESSION_ALGORITHMS
+        .iter()
+        .map(|x| x.as_ref().to_string())
+        .collect()
+}
+
+#[napi]
+pub fn supported_key_types() -> Vec<String> {
+    std::iter::once(&russh_keys::key::ED25519)
+        .chain(russh_keys::key::ALL_KEY_TYPES.iter().copied())
+        .map(|x| x.as_ref().to_string())
+        .collect()
+}
+
+/// A full set of algorithm preferences, in order of preference.
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct AlgorithmPreset {
+    pub cipher_algos: Vec<String>,
+    pub kex_algos: Vec<String>,
+    pub key_algos: Vec<String>,
+    pub mac_algos: Vec<String>,
+    pub compression_algos: Vec<String>,
+}
+
+const PRESET_NAMES: &[&str] = &["modern", "compatible", "legacy"];
+
+const MODERN_CIPHERS: &[&str] = &["chacha20-poly1305@openssh.com", "aes256-gcm@openssh.com"];
+const COMPATIBLE_CIPHERS: &[&str] = &["aes256-ctr", "aes192-ctr", "aes128-ctr"];
+const LEGACY_CIPHERS: &[&str] = &["aes256-cbc", "aes192-cbc", "aes128-cbc", "3des-cbc"];
+
+const MODERN_KEX: &[&str] = &[
+    "curve25519-sha256",
+    "curve25519-sha256@libssh.org",
+    "diffie-hellman-group16-sha512",
+];
+const COMPATIBLE_KEX: &[&str] = &[
+    "ecdh-sha2-nistp256",
+    "ecdh-sha2-nistp384",
+    "ecdh-sha2-nistp521",
+    "diffie-hellman-group14-sha256",
+];
+const LEGACY_KEX: &[&str] = &["diffie-hellman-group14-sha1", "diffie-hellman-group1-sha1"];
+
+const MODERN_KEYS: &[&str] = &[
+    "ssh-ed25519",
+    "ecdsa-sha2-nistp256",
+    "ecdsa-sha2-nistp384",
+    "ecdsa-sha2-nistp521",
+    "rsa-sha2-512",
+    "rsa-sha2-256",
+];
+const LEGACY_KEYS: &[&str] = &["ssh-rsa"];
+
+const MODERN_MACS: &[&str] = &[
+    "hmac-sha2-512-etm@openssh.com",
+    "hmac-sha2-256-etm@openssh.com",
+];
+const COMPATIBLE_MACS: &[&str] = &["hmac-sha2-512", "hmac-sha2-256"];
+const LEGACY_MACS: &[&str] = &["hmac-sha1-etm@openssh.com", "hmac-sha1"];
+
+const COMPRESSION: &[&str] = &["none"];
+const LEGACY_COMPRESSION: &[&str] = &["zlib@openssh.com", "zlib"];
+
+const NO_ADDITIONS: &[&str] = &[];
+
+/// Concatenates the given name lists, keeping only what this build supports.
+fn preset_list(lists: &[&[&str]], supported: Vec<String>) -> Vec<String> {
+    lists
+        .iter()
+        .flat_map(|list| list.iter())
+        .filter(|name| supported.iter().any(|x| x == *name))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Returns the names accepted by `algorithmPreset()` and `ConnectOptions.algorithmPreset`.
+#[napi]
+pub fn algorithm_presets() -> Vec<String> {
+    PRESET_NAMES.iter().map(|x| x.to_string()).collect()
+}
+
+/// Returns the algorithm lists for a named preset:
+///
+/// * `modern` - only AEAD ciphers, curve25519/large DH groups and ETM MACs
+/// * `compatible` - adds NIST curves, CTR ciphers and non-ETM SHA-2 MACs
+/// * `legacy` - adds SHA-1, CBC ciphers and `ssh-rsa` for very old servers
+#[napi]
+pub fn algorithm_preset(name: String) -> napi::Result<AlgorithmPreset> {
+    let level = PRESET_NAMES
+        .iter()
+        .position(|x| *x == name)
+        .ok_or_else(|| {
+            napi::Error::new(
+                napi::Status::InvalidArg,
+                format!(
+                    "Unknown algorithm preset: {name}. Known presets: {}",
+                    PRESET_NAMES.join(", ")
+                ),
+            )
+        })?;
+    let pick = |lists: &[&'static [&'static str]; 3]| lists[..=level].to_vec();
+
+    Ok(AlgorithmPreset {
+        cipher_algos: preset_list(
+            &pick(&[MODERN_CIPHERS, COMPATIBLE_CIPHERS, LEGACY_CIPHERS]),
+            supported_ciphers(),
+        ),
+        kex_algos: preset_list(
+            &pick(&[MODERN_KEX, COMPATIBLE_KEX, LEGACY_KEX]),
+            supported_kex_algorithms(),
+        ),
+        key_algos: preset_list(
+            &pick(&[MODERN_KEYS, NO_ADDITIONS, LEGACY_KEYS]),
+            supported_key_types(),
+        ),
+        mac_algos: preset_list(
+            &pick(&[MODERN_MACS, COMPATIBLE_MACS, LEGACY_MACS]),
+            supported_macs(),
+        ),
+        compression_algos: preset_list(
+            &pick(&[COMPRESSION, NO_ADDITIONS, LEGACY_COMPRESSION]),
+            supported_compression_algorithms(),
+        ),
+    })
+}
+
+fn parse_key_algo(name: &str) -> Option<russh_keys::key::Name> {
+    if name == russh_keys::key::ED25519.as_ref() {
+        return Some(russh_keys::key::ED25519);
+    }
+    russh_keys::key::Name::try_from(name).ok()
+}
+
+fn parse_kex_algo(name: &str) -> Option<russh::kex::Name> {
+    [
+        russh::kex::EXTENSION_SUPPORT_AS_CLIENT,
+        russh::kex::EXTENSION_OPENSSH_STRICT_KEX_AS_CLIENT,
+    ]
+    .iter()
+    .find(|x| x.as_ref() == name)
+    .copied()
+    .or_else(|| russh::kex::Name::try_from(name).ok())
+}
+
+/// Collects the errors for every category before failing, so that a single
+/// call reports all unknown names at once.
+#[derive(Default)]
+struct Validator {
+    errors: Vec<String>,
+}
+
+impl Validator {
+    fn parse<N>(
+        &mut self,
+        category: &str,
+        names: &[String],
+        parse: impl Fn(&str) -> Option<N>,
+    ) -> Vec<N> {
+        let mut parsed = vec![];
+        let mut unknown = vec![];
+        for name in names {
+            match parse(name) {
+                Some(x) => parsed.push(x),
+                None => unknown.push(name.as_str()),
+            }
+        }
+        if !unknown.is_empty() {
+            self.errors.push(format!(
+                "unknown {category} algorithm(s): {}",
+                unknown.join(", ")
+            ));
+        } else if parsed.is_empty() {
+            self.errors
+                .push(format!("{category} algorithm list must not be empty"));
+        }
+        parsed
+    }
+
+    fn finish(self) -> napi::Result<()> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        Err(napi::Error::new(
+            napi::Status::InvalidArg,
+            format!(
+                "Invalid algorithm configuration: {}",
+                self.errors.join("; ")
+            ),
+        ))
+    }
+}
+
+/// User-facing algorithm lists, as found in `ConnectOptions`.
+pub(crate) struct AlgorithmLists<'a> {
+    pub preset: Option<&'a str>,
+    pub cipher: Option<&'a Vec<String>>,
+    pub kex: Option<&'a Vec<String>>,
+    pub key: Option<&'a Vec<String>>,
+    pub mac: Option<&'a Vec<String>>,
+    pub compression: Option<&'a Vec<String>>,
+}
+
+impl AlgorithmLists<'_> {
+    /// Builds russh preferences, starting from the preset (or russh's
+    /// defaults) and replacing each category the caller listed explicitly.
+    pub(crate) fn to_preferred(&self) -> napi::Result<russh::Preferred> {
+        let preset = self
+            .preset
+            .map(|name| algorithm_preset(name.into()))
+            .transpose()?;
+        let mut preferred = russh::Preferred::DEFAULT.clone();
+        let mut validator = Validator::default();
+
+        if let Some(names) = self.cipher.or(preset.as_ref().map(|x| &x.cipher_algos)) {
+            preferred.cipher = Cow::Owned(
+                validator.parse("cipher", names, |x| russh::cipher::Name::try_from(x).ok()),
+            );
+        }
+        if let Some(names) = self.kex.or(preset.as_ref().map(|x| &x.kex_algos)) {
+            let mut kex = validator.parse("kex", names, parse_kex_algo);
+            // Custom lists would otherwise silently lose extension
+            // negotiation and the strict-KEX (Terrapin) countermeasure
+            for marker in [
+                russh::kex::EXTENSION_SUPPORT_AS_CLIENT,
+                russh::kex::EXTENSION_OPENSSH_STRICT_KEX_AS_CLIENT,
+            ] {
+                if !kex.contains(&marker) {
+                    kex.push(marker);
+                }
+            }
+            preferred.kex = Cow::Owned(kex);
+        }
+        if let Some(names) = self.key.or(preset.as_ref().map(|x| &x.key_algos)) {
+            preferred.key = Cow::Owned(validator.parse("host key", names, parse_key_algo));
+        }
+        if let Some(names) = self.mac.or(preset.as_ref().map(|x| &x.mac_algos)) {
+            preferred.mac =
+                Cow::Owned(validator.parse("MAC", names, |x| russh::mac::Name::try_from(x).ok()));
+        }
+        if let Some(names) = self
+            .compression
+            .or(preset.as_ref().map(|x| &x.compression_algos))
+        {
+            preferred.compression = Cow::Owned(validator.parse("compression", names, |x| {
+                russh::compression::Name::try_from(x).ok()
+            }));
+        }
+
+        validator.finish()?;
+        Ok(preferred)
+    }
+}
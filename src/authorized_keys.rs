@@ -0,0 +1,189 @@
+use data_encoding::BASE64;
+use napi_derive::napi;
+
+use crate::key::{next_field, public_key_from_blob, SshPublicKey};
+use crate::known_hosts::blob_key_type;
+use crate::line_file::LineFile;
+
+/// A key line of an `authorized_keys` file.
+#[napi(object, object_from_js = false)]
+pub struct AuthorizedKey {
+    /// Index of the line in the file.
+    pub line: u32,
+    pub key_type: String,
+    /// `null` for key types this library can't use, e.g. security keys.
+    pub key: Option<SshPublicKey>,
+    pub comment: String,
+    /// Options as written, e.g. `command="uptime"` or `no-pty`.
+    pub options: Vec<String>,
+    /// Patterns from `from="..."`.
+    pub from: Option<Vec<String>>,
+    pub command: Option<String>,
+    pub restrict: bool,
+    /// `host:port` values of all `permitopen="..."` options.
+    pub permit_open: Vec<String>,
+    /// `YYYYMMDD[HHMM[SS]]` timestamp from `expiry-time="..."`.
+    pub expiry_time: Option<String>,
+}
+
+pub(crate) struct Entry {
+    pub(crate) options: Vec<String>,
+    key_type: String,
+    pub(crate) key: Vec<u8>,
+    comment: String,
+}
+
+impl Entry {
+    /// Value of option `name`, unquoted.
+    pub(crate) fn option_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = String> + 'a {
+        self.options.iter().filter_map(move |option| {
+            let (key, value) = option.split_once('=')?;
+            if !key.eq_ignore_ascii_case(name) {
+                return None;
+            }
+            let value = value.strip_prefix('"')?.strip_suffix('"')?;
+            Some(value.replace("\\\"", "\""))
+        })
+    }
+
+    fn to_authorized_key(&self, line: usize) -> AuthorizedKey {
+        AuthorizedKey {
+            line: line as u32,
+            key_type: self.key_type.clone(),
+            key: public_key_from_blob(&self.key)
+                .ok()
+                .map(|x| x.with_comment(&self.comment)),
+            comment: self.comment.clone(),
+            options: self.options.clone(),
+            from: self
+                .option_values("from")
+                .next()
+                .map(|x| x.split(',').map(Into::into).collect()),
+            command: self.option_values("command").next(),
+            restrict: self
+                .options
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case("restrict")),
+            permit_open: self.option_values("permitopen").collect(),
+            expiry_time: self.option_values("expiry-time").next(),
+        }
+    }
+}
+
+/// Splits the leading option list off `text`, honouring quotes and `\"`.
+fn split_options(text: &str) -> Option<(Vec<String>, &str)> {
+    let mut options = vec![];
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                options.push(text[start..i].to_string());
+                start = i + 1;
+            }
+            c if c.is_whitespace() && !quoted => {
+                options.push(text[start..i].to_string());
+                return Some((options, &text[i..]));
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+fn parse_key(text: &str) -> Option<(String, Vec<u8>, String)> {
+    let (key_type, rest) = next_field(text)?;
+    key_type.parse::<ssh_key::Algorithm>().ok()?;
+    let (base64, comment) = next_field(rest)?;
+    let key = BASE64.decode(base64.as_bytes()).ok()?;
+    Some((key_type.to_string(), key, comment.trim().to_string()))
+}
+
+pub(crate) fn parse_entry(text: &str) -> Option<Entry> {
+    let text = text.trim_start();
+    if text.is_empty() || text.starts_with('#') {
+        return None;
+    }
+    // Like sshd, only look for options if the line doesn't start with a key
+    let (options, (key_type, key, comment)) = match parse_key(text) {
+        Some(key) => (vec![], key),
+        None => {
+            let (options, rest) = split_options(text)?;
+            (options, parse_key(rest)?)
+        }
+    };
+    Some(Entry {
+        options,
+        key_type,
+        key,
+        comment,
+    })
+}
+
+/// An OpenSSH `authorized_keys` file.
+#[napi]
+pub struct AuthorizedKeys {
+    file: LineFile<Entry>,
+}
+
+#[napi]
+impl AuthorizedKeys {
+    #[napi(factory)]
+    pub fn parse(content: String) -> Self {
+        Self {
+            file: LineFile::parse(&content, parse_entry),
+        }
+    }
+
+    #[napi]
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        self.file.to_string()
+    }
+
+    #[napi]
+    pub fn keys(&self) -> Vec<AuthorizedKey> {
+        self.file
+            .lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| Some(x.entry.as_ref()?.to_authorized_key(i)))
+            .collect()
+    }
+
+    /// Appends a line for `key`. `options` are written as given, e.g.
+    /// `["restrict", "command=\"uptime\""]`.
+    #[napi]
+    pub fn add(
+        &mut self,
+        key: &SshPublicKey,
+        options: Option<Vec<String>>,
+        comment: Option<String>,
+    ) {
+        let blob = key.blob();
+        let key_type = String::from_utf8_lossy(blob_key_type(&blob).unwrap_or_default());
+        let mut line = format!("{key_type} {}", BASE64.encode(&blob));
+        if let Some(options) = options.filter(|x| !x.is_empty()) {
+            line = format!("{} {line}", options.join(","));
+        }
+        if let Some(comment) = comment.filter(|x| !x.is_empty()) {
+            line = format!("{line} {comment}");
+        }
+
+        self.file.push(&line);
+    }
+
+    /// Removes every line for `key` and returns how many were removed.
+    #[napi]
+    pub fn remove(&mut self, key: &SshPublicKey) -> u32 {
+        let blob = key.blob();
+        let lines = &mut self.file.lines;
+        let before = lines.len();
+        lines.retain(|x| x.entry.as_ref().map_or(true, |x| x.key != blob));
+        (before - lines.len()) as u32
+    }
+}
@@ -0,0 +1,118 @@
+use std::convert::TryFrom;
+
+use napi::bindgen_prelude::BigInt;
+use napi_derive::napi;
+use ssh_key::certificate::CertType;
+use ssh_key::public::KeyData;
+
+use crate::error::WrappedError;
+use crate::key::SshPublicKey;
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug)]
+pub enum CertificateType {
+    User,
+    Host,
+}
+
+/// An OpenSSH certificate (`*-cert-v01@openssh.com`).
+#[napi]
+#[derive(Clone)]
+pub struct SshCertificate {
+    pub(crate) inner: ssh_key::Certificate,
+}
+
+pub(crate) fn ssh_key_error(err: ssh_key::Error) -> WrappedError {
+    russh_keys::Error::from(err).into()
+}
+
+pub(crate) fn public_key_from_data(data: &KeyData) -> napi::Result<SshPublicKey> {
+    russh_keys::key::PublicKey::try_from(data)
+        .map(Into::into)
+        .map_err(|e| WrappedError::from(e).into())
+}
+
+impl SshCertificate {
+    pub(crate) fn certifies(&self, key: &SshPublicKey) -> bool {
+        ssh_key::PublicKey::from_bytes(&key.blob())
+            .map_or(false, |x| x.key_data() == self.inner.public_key())
+    }
+}
+
+#[napi]
+impl SshCertificate {
+    /// Parses a certificate in OpenSSH format, e.g. the contents of
+    /// `id_ed25519-cert.pub`.
+    #[napi(factory)]
+    pub fn parse(data: String) -> napi::Result<Self> {
+        let inner = ssh_key::Certificate::from_openssh(data.trim()).map_err(ssh_key_error)?;
+        Ok(Self { inner })
+    }
+
+    #[napi]
+    pub fn algorithm(&self) -> String {
+        self.inner.algorithm().to_certificate_type().to_string()
+    }
+
+    #[napi]
+    pub fn cert_type(&self) -> CertificateType {
+        match self.inner.cert_type() {
+            CertType::User => CertificateType::User,
+            CertType::Host => CertificateType::Host,
+        }
+    }
+
+    #[napi]
+    pub fn key_id(&self) -> String {
+        self.inner.key_id().into()
+    }
+
+    #[napi]
+    pub fn serial(&self) -> BigInt {
+        self.inner.serial().into()
+    }
+
+    #[napi]
+    pub fn comment(&self) -> String {
+        self.inner.comment().into()
+    }
+
+    /// An empty list means the certificate is valid for any principal.
+    #[napi]
+    pub fn valid_principals(&self) -> Vec<String> {
+        self.inner.valid_principals().to_vec()
+    }
+
+    /// Start of the validity window, in seconds since the Unix epoch.
+    #[napi]
+    pub fn valid_after(&self) -> i64 {
+        self.inner.valid_after().min(i64::MAX as u64) as i64
+    }
+
+    /// End of the validity window, in seconds since the Unix epoch, or
+    /// `null` if the certificate never expires.
+    #[napi]
+    pub fn valid_before(&self) -> Option<i64> {
+        match self.inner.valid_before() {
+            u64::MAX => None,
+            x => Some(x.min(i64::MAX as u64) as i64),
+        }
+    }
+
+    /// The certified key.
+    #[napi]
+    pub fn public_key(&self) -> napi::Result<SshPublicKey> {
+        public_key_from_data(self.inner.public_key())
+    }
+
+    /// The key of the CA that signed the certificate.
+    #[napi]
+    pub fn signature_key(&self) -> napi::Result<SshPublicKey> {
+        public_key_from_data(self.inner.signature_key())
+    }
+
+    #[napi]
+    pub fn to_openssh(&self) -> napi::Result<String> {
+        Ok(self.inner.to_openssh().map_err(ssh_key_error)?)
+    }
+}
@@ -4,7 +4,7 @@ use napi::bindgen_prelude::Uint8Array;
 use napi_derive::napi;
 use tokio::sync::Mutex;
 
-use crate::error::WrappedError;
+use crate::error::{ErrorCategory, ErrorDetails, WrappedError};
 
 #[napi]
 pub struct SshChannel {
@@ -23,10 +23,12 @@ macro_rules! lock_channel {
     ($self: expr, $handle: ident) => {
         let locked = $self.handle.lock().await;
         let Some(ref $handle) = *locked else {
-            return Err(napi::Error::new(
-                napi::Status::GenericFailure,
+            return Err(ErrorDetails::new(
+                "ERR_CHANNEL_CONSUMED",
+                ErrorCategory::Channel,
                 "Channel is already consumed",
-            ));
+            )
+            .into());
         };
     };
 }
@@ -135,8 +137,9 @@ impl SshChannel {
     pub async fn data(&self, data: Uint8Array) -> napi::Result<()> {
         lock_channel!(self, handle);
         handle.data(&data[..]).await.map_err(|_| {
-            napi::Error::new(
-                napi::Status::GenericFailure,
+            ErrorDetails::new(
+                "ERR_CHANNEL_CLOSED",
+                ErrorCategory::Channel,
                 "Failed to send data to channel",
             )
         })?;
@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use napi::bindgen_prelude::{FromNapiValue, Object, Promise, Uint8Array};
+use napi::threadsafe_function::ThreadsafeFunction;
+use napi::{Env, NapiRaw};
+use napi_derive::napi;
+
+use crate::algorithms::AlgorithmLists;
+use crate::channel::SshChannel;
+use crate::disconnect::DisconnectEvent;
+use crate::key::SshPublicKey;
+
+/// Connection settings passed to `connect()`. Every field is optional and
+/// falls back to russh's defaults.
+#[napi(object, object_to_js = false)]
+#[derive(Default)]
+pub struct ConnectOptions {
+    /// One of `algorithmPresets()`. Explicit algorithm lists below
+    /// take precedence over the preset's lists.
+    pub algorithm_preset: Option<String>,
+    pub cipher_algos: Option<Vec<String>>,
+    pub kex_algos: Option<Vec<String>>,
+    pub key_algos: Option<Vec<String>>,
+    pub mac_algos: Option<Vec<String>>,
+    pub compression_algos: Option<Vec<String>>,
+    pub connection_timeout_seconds: Option<u32>,
+    pub keepalive_interval_seconds: Option<u32>,
+    pub keepalive_max: Option<u32>,
+    pub inactivity_timeout_seconds: Option<u32>,
+    pub window_size: Option<u32>,
+    pub maximum_packet_size: Option<u32>,
+    pub rekey_write_limit: Option<u32>,
+    pub rekey_read_limit: Option<u32>,
+    pub rekey_time_limit_seconds: Option<u32>,
+    /// Sets `TCP_NODELAY` on socket-based transports.
+    pub nodelay: Option<bool>,
+    /// Full identification string, e.g. `SSH-2.0-MyClient_1.0`.
+    pub client_id: Option<String>,
+}
+
+const CONNECT_OPTIONS_FIELDS: &[&str] = &[
+    "algorithmPreset",
+    "cipherAlgos",
+    "kexAlgos",
+    "keyAlgos",
+    "macAlgos",
+    "compressionAlgos",
+    "connectionTimeoutSeconds",
+    "keepaliveIntervalSeconds",
+    "keepaliveMax",
+    "inactivityTimeoutSeconds",
+    "windowSize",
+    "maximumPacketSize",
+    "rekeyWriteLimit",
+    "rekeyReadLimit",
+    "rekeyTimeLimitSeconds",
+    "nodelay",
+    "clientId",
+];
+
+/// Callbacks through which the client reports server events to JS.
+#[napi(object, object_to_js = false)]
+pub struct ClientHandlerCallbacks {
+    /// Always gets a plain key: russh can't negotiate the
+    /// `*-cert-v01@openssh.com` host key algorithms, so host certificates
+    /// never reach it.
+    pub server_key_callback: ThreadsafeFunction<SshPublicKey, Promise<bool>>,
+    pub data_callback: ThreadsafeFunction<(u32, Uint8Array)>,
+    pub extended_data_callback: ThreadsafeFunction<(u32, u32, Uint8Array)>,
+    pub eof_callback: ThreadsafeFunction<u32>,
+    pub close_callback: ThreadsafeFunction<u32>,
+    pub exit_status_callback: ThreadsafeFunction<(u32, u32)>,
+    pub exit_signal_callback: ThreadsafeFunction<(u32, String, bool, String, String)>,
+    pub disconnect_callback: ThreadsafeFunction<DisconnectEvent>,
+    pub x11_channel_open_callback: ThreadsafeFunction<(SshChannel, String, u32)>,
+    pub tcpip_channel_open_callback: ThreadsafeFunction<(SshChannel, String, u32, String, u32)>,
+    pub agent_channel_open_callback: ThreadsafeFunction<SshChannel>,
+    pub banner_callback: ThreadsafeFunction<String>,
+}
+
+const CLIENT_HANDLER_CALLBACKS_FIELDS: &[&str] = &[
+    "serverKeyCallback",
+    "dataCallback",
+    "extendedDataCallback",
+    "eofCallback",
+    "closeCallback",
+    "exitStatusCallback",
+    "exitSignalCallback",
+    "disconnectCallback",
+    "x11ChannelOpenCallback",
+    "tcpipChannelOpenCallback",
+    "agentChannelOpenCallback",
+    "bannerCallback",
+];
+
+fn invalid_arg<S: Into<String>>(message: S) -> napi::Error {
+    napi::Error::new(napi::Status::InvalidArg, message.into())
+}
+
+/// Converts a JS object into a `#[napi(object)]` struct, refusing any
+/// property the struct does not declare.
+fn from_object_strict<T: FromNapiValue>(
+    env: &Env,
+    object: Object,
+    name: &str,
+    known_fields: &[&str],
+) -> napi::Result<T> {
+    let unknown = Object::keys(&object)?
+        .into_iter()
+        .filter(|key| !known_fields.contains(&key.as_str()))
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        return Err(invalid_arg(format!(
+            "Unknown {name} field(s): {}. Known fields: {}",
+            unknown.join(", "),
+            known_fields.join(", "),
+        )));
+    }
+    unsafe { T::from_napi_value(env.raw(), object.raw()) }
+}
+
+impl ConnectOptions {
+    pub(crate) fn from_object(env: &Env, object: Option<Object>) -> napi::Result<Self> {
+        match object {
+            Some(object) => {
+                from_object_strict(env, object, "ConnectOptions", CONNECT_OPTIONS_FIELDS)
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub(crate) fn to_russh_config(&self) -> napi::Result<russh::client::Config> {
+        let preferred = AlgorithmLists {
+            preset: self.algorithm_preset.as_deref(),
+            cipher: self.cipher_algos.as_ref(),
+            kex: self.kex_algos.as_ref(),
+            key: self.key_algos.as_ref(),
+            mac: self.mac_algos.as_ref(),
+            compression: self.compression_algos.as_ref(),
+        }
+        .to_preferred()?;
+
+        let mut cfg = russh::client::Config {
+            preferred,
+            keepalive_interval: self
+                .keepalive_interval_seconds
+                .map(|x| Duration::from_secs(x as u64)),
+            inactivity_timeout: self
+                .inactivity_timeout_seconds
+                .map(|x| Duration::from_secs(x as u64)),
+            ..Default::default()
+        };
+
+        if let Some(keepalive_max) = self.keepalive_max {
+            cfg.keepalive_max = keepalive_max as usize;
+        }
+        if let Some(window_size) = self.window_size {
+            cfg.window_size = window_size;
+        }
+        if let Some(maximum_packet_size) = self.maximum_packet_size {
+            if maximum_packet_size > 65535 {
+                return Err(invalid_arg("maximumPacketSize must not exceed 65535"));
+            }
+            cfg.maximum_packet_size = maximum_packet_size;
+        }
+
+        for (name, limit) in [
+            ("rekeyWriteLimit", self.rekey_write_limit),
+            ("rekeyReadLimit", self.rekey_read_limit),
+        ] {
+            if limit.map_or(false, |x| x > 1 << 30) {
+                return Err(invalid_arg(format!("{name} must not exceed 1 GiB")));
+            }
+        }
+        cfg.limits = russh::Limits::new(
+            self.rekey_write_limit
+                .map_or(cfg.limits.rekey_write_limit, |x| x as usize),
+            self.rekey_read_limit
+                .map_or(cfg.limits.rekey_read_limit, |x| x as usize),
+            self.rekey_time_limit_seconds
+                .map_or(cfg.limits.rekey_time_limit, |x| {
+                    Duration::from_secs(x as u64)
+                }),
+        );
+
+        if let Some(client_id) = &self.client_id {
+            if !client_id.starts_with("SSH-2.0-") || client_id.contains(['\r', '\n']) {
+                return Err(invalid_arg(
+                    "clientId must be a single line starting with SSH-2.0-",
+                ));
+            }
+            cfg.client_id = russh::SshId::Standard(client_id.clone());
+        }
+
+        Ok(cfg)
+    }
+}
+
+impl ClientHandlerCallbacks {
+    pub(crate) fn from_object(env: &Env, object: Object) -> napi::Result<Self> {
+        from_object_strict(
+            env,
+            object,
+            "ClientHandlerCallbacks",
+            CLIENT_HANDLER_CALLBACKS_FIELDS,
+        )
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::{Arc, Mutex};
+
+use napi_derive::napi;
+use russh::client::{DisconnectReason, RemoteDisconnectInfo};
+
+use crate::error::WrappedError;
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug)]
+pub enum DisconnectInitiator {
+    /// The server sent SSH_MSG_DISCONNECT.
+    Server,
+    /// `SshClient.disconnect()` was called.
+    Client,
+    /// The connection failed or was lost.
+    Error,
+}
+
+/// SSH_MSG_DISCONNECT reason codes from RFC 4253 section 11.1.
+#[napi(string_enum = "SCREAMING_SNAKE_CASE")]
+#[derive(Debug)]
+pub enum DisconnectReasonCode {
+    HostNotAllowedToConnect,
+    ProtocolError,
+    KeyExchangeFailed,
+    MacError,
+    CompressionError,
+    ServiceNotAvailable,
+    ProtocolVersionNotSupported,
+    HostKeyNotVerifiable,
+    ConnectionLost,
+    ByApplication,
+    TooManyConnections,
+    AuthCancelledByUser,
+    NoMoreAuthMethodsAvailable,
+    IllegalUserName,
+}
+
+impl From<DisconnectReasonCode> for russh::Disconnect {
+    fn from(value: DisconnectReasonCode) -> Self {
+        match value {
+            DisconnectReasonCode::HostNotAllowedToConnect => Self::HostNotAllowedToConnect,
+            DisconnectReasonCode::ProtocolError => Self::ProtocolError,
+            DisconnectReasonCode::KeyExchangeFailed => Self::KeyExchangeFailed,
+            DisconnectReasonCode::MacError => Self::MACError,
+            DisconnectReasonCode::CompressionError => Self::CompressionError,
+            DisconnectReasonCode::ServiceNotAvailable => Self::ServiceNotAvailable,
+            DisconnectReasonCode::ProtocolVersionNotSupported => Self::ProtocolVersionNotSupported,
+            DisconnectReasonCode::HostKeyNotVerifiable => Self::HostKeyNotVerifiable,
+            DisconnectReasonCode::ConnectionLost => Self::ConnectionLost,
+            DisconnectReasonCode::ByApplication => Self::ByApplication,
+            DisconnectReasonCode::TooManyConnections => Self::TooManyConnections,
+            DisconnectReasonCode::AuthCancelledByUser => Self::AuthCancelledByUser,
+            DisconnectReasonCode::NoMoreAuthMethodsAvailable => Self::NoMoreAuthMethodsAvailable,
+            DisconnectReasonCode::IllegalUserName => Self::IllegalUserName,
+        }
+    }
+}
+
+impl DisconnectReasonCode {
+    fn from_russh(value: &russh::Disconnect) -> Option<Self> {
+        use russh::Disconnect;
+        Some(match value {
+            Disconnect::HostNotAllowedToConnect => Self::HostNotAllowedToConnect,
+            Disconnect::ProtocolError => Self::ProtocolError,
+            Disconnect::KeyExchangeFailed => Self::KeyExchangeFailed,
+            Disconnect::Reserved => return None,
+            Disconnect::MACError => Self::MacError,
+            Disconnect::CompressionError => Self::CompressionError,
+            Disconnect::ServiceNotAvailable => Self::ServiceNotAvailable,
+            Disconnect::ProtocolVersionNotSupported => Self::ProtocolVersionNotSupported,
+            Disconnect::HostKeyNotVerifiable => Self::HostKeyNotVerifiable,
+            Disconnect::ConnectionLost => Self::ConnectionLost,
+            Disconnect::ByApplication => Self::ByApplication,
+            Disconnect::TooManyConnections => Self::TooManyConnections,
+            Disconnect::AuthCancelledByUser => Self::AuthCancelledByUser,
+            Disconnect::NoMoreAuthMethodsAvailable => Self::NoMoreAuthMethodsAvailable,
+            Disconnect::IllegalUserName => Self::IllegalUserName,
+        })
+    }
+}
+
+/// Passed to the disconnect callback once the session has ended.
+#[napi(object, object_from_js = false)]
+pub struct DisconnectEvent {
+    pub initiator: DisconnectInitiator,
+    pub reason: Option<DisconnectReasonCode>,
+    /// Numeric reason code as sent on the wire.
+    pub reason_code: Option<u32>,
+    pub description: Option<String>,
+    pub language_tag: Option<String>,
+    /// Only set when `initiator` is `error`.
+    pub error: Option<napi::Error>,
+}
+
+/// Remembers what `SshClient.disconnect()` sent, since russh reports a
+/// locally initiated disconnect the same way as a lost connection.
+#[derive(Clone, Default)]
+pub(crate) struct LocalDisconnect(Arc<Mutex<Option<(DisconnectReasonCode, String)>>>);
+
+impl LocalDisconnect {
+    pub(crate) fn set(&self, reason: DisconnectReasonCode, description: String) {
+        if let Ok(mut local) = self.0.lock() {
+            local.get_or_insert((reason, description));
+        }
+    }
+
+    pub(crate) fn to_event(&self, reason: DisconnectReason<WrappedError>) -> DisconnectEvent {
+        match reason {
+            DisconnectReason::ReceivedDisconnect(RemoteDisconnectInfo {
+                reason_code,
+                message,
+                lang_tag,
+            }) => DisconnectEvent {
+                initiator: DisconnectInitiator::Server,
+                reason: DisconnectReasonCode::from_russh(&reason_code),
+                reason_code: Some(reason_code as u32),
+                description: Some(message),
+                language_tag: Some(lang_tag),
+                error: None,
+            },
+            DisconnectReason::Error(err) => match self.0.lock().ok().and_then(|x| x.clone()) {
+                Some((reason, description)) => DisconnectEvent {
+                    initiator: DisconnectInitiator::Client,
+                    reason: Some(reason),
+                    reason_code: Some(russh::Disconnect::from(reason) as u32),
+                    description: Some(description),
+                    language_tag: None,
+                    error: None,
+                },
+                None => DisconnectEvent {
+                    initiator: DisconnectInitiator::Error,
+                    reason: None,
+                    reason_code: None,
+                    description: None,
+                    language_tag: None,
+                    error: Some(err.into()),
+                },
+            },
+        }
+    }
+}
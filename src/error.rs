@@ -1,4 +1,6 @@
 use russh::AgentAuthError;
+use russh_sftp::protocol::StatusCode;
+use serde::Serialize;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -26,23 +28,249 @@ pub enum WrappedError {
 
     #[error(transparent)]
     Node(#[from] napi::Error),
+
+    #[error("{}", .0.message)]
+    Details(ErrorDetails),
+}
+
+impl WrappedError {
+    /// The `code` the error will have in JS, if it's already known.
+    pub(crate) fn code(&self) -> Option<&'static str> {
+        match self {
+            WrappedError::Details(details) => Some(details.code),
+            _ => None,
+        }
+    }
+}
+
+impl From<russh::SendError> for WrappedError {
+    fn from(_: russh::SendError) -> Self {
+        russh::Error::SendError.into()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorCategory {
+    Transport,
+    Proxy,
+    Kex,
+    Auth,
+    Channel,
+    Sftp,
+    Agent,
+    /// Reading, decrypting or encoding keys, outside of authentication.
+    Key,
+    /// Invalid arguments from the caller.
+    Argument,
+}
+
+/// Machine-readable error description. It travels to JS as JSON in the
+/// error message and is unpacked into a `RusshError` by `lib/error.ts`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorDetails {
+    /// Stable identifier such as `ERR_HOST_KEY_REJECTED`.
+    pub code: &'static str,
+    pub category: ErrorCategory,
+    /// Name of the underlying `std::io::ErrorKind`, e.g. `ConnectionRefused`.
+    pub io_kind: Option<String>,
+    /// SFTP status code (`SSH_FX_*`) returned by the server.
+    pub sftp_status: Option<u32>,
+    pub message: String,
+}
+
+impl ErrorDetails {
+    pub fn new<S: ToString>(code: &'static str, category: ErrorCategory, message: S) -> Self {
+        Self {
+            code,
+            category,
+            io_kind: None,
+            sftp_status: None,
+            message: message.to_string(),
+        }
+    }
+
+    pub(crate) fn from_io(err: &std::io::Error, category: ErrorCategory) -> Self {
+        use std::io::ErrorKind;
+        let code = match err.kind() {
+            ErrorKind::ConnectionRefused => "ERR_CONNECTION_REFUSED",
+            ErrorKind::TimedOut => "ERR_TIMEOUT",
+            ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::BrokenPipe
+            | ErrorKind::UnexpectedEof => "ERR_CONNECTION_LOST",
+            ErrorKind::NotFound => "ERR_NOT_FOUND",
+            ErrorKind::PermissionDenied => "ERR_PERMISSION_DENIED",
+            _ => "ERR_IO",
+        };
+        Self {
+            io_kind: Some(format!("{:?}", err.kind())),
+            ..Self::new(code, category, err)
+        }
+    }
+
+    /// For blocking work that panicked or was cancelled.
+    pub(crate) fn from_join(err: &tokio::task::JoinError, category: ErrorCategory) -> Self {
+        Self::new("ERR_INTERNAL", category, err)
+    }
+
+    fn from_russh(err: &russh::Error) -> Self {
+        use russh::Error;
+        let (code, category) = match err {
+            Error::IO(err) => return Self::from_io(err, ErrorCategory::Transport),
+            Error::Keys(err) => return Self::from_keys(err, ErrorCategory::Auth),
+            Error::NoCommonAlgo { kind, ours, theirs } => {
+                return Self::new(
+                    "ERR_NO_COMMON_ALGORITHM",
+                    ErrorCategory::Kex,
+                    format!(
+                        "No common {kind:?} algorithm (ours: {}, theirs: {})",
+                        ours.join(", "),
+                        theirs.join(", ")
+                    ),
+                )
+            }
+            Error::KexInit | Error::Kex | Error::UnknownAlgo => {
+                ("ERR_KEX_FAILED", ErrorCategory::Kex)
+            }
+            Error::UnknownKey => ("ERR_HOST_KEY_REJECTED", ErrorCategory::Kex),
+            Error::WrongServerSig => ("ERR_HOST_KEY_SIGNATURE", ErrorCategory::Kex),
+            Error::KeyChanged { .. } => ("ERR_HOST_KEY_CHANGED", ErrorCategory::Kex),
+            Error::StrictKeyExchangeViolation { .. } => {
+                ("ERR_STRICT_KEX_VIOLATION", ErrorCategory::Kex)
+            }
+            Error::NotAuthenticated => ("ERR_NOT_AUTHENTICATED", ErrorCategory::Auth),
+            Error::NoAuthMethod => ("ERR_NO_AUTH_METHOD", ErrorCategory::Auth),
+            Error::WrongChannel => ("ERR_CHANNEL_NOT_OPEN", ErrorCategory::Channel),
+            Error::ChannelOpenFailure(_) => ("ERR_CHANNEL_OPEN_FAILED", ErrorCategory::Channel),
+            Error::RequestDenied => ("ERR_REQUEST_DENIED", ErrorCategory::Channel),
+            Error::Disconnect | Error::HUP | Error::SendError => {
+                ("ERR_DISCONNECTED", ErrorCategory::Transport)
+            }
+            Error::ConnectionTimeout | Error::Elapsed(_) => {
+                ("ERR_TIMEOUT", ErrorCategory::Transport)
+            }
+            Error::KeepaliveTimeout => ("ERR_KEEPALIVE_TIMEOUT", ErrorCategory::Transport),
+            Error::InactivityTimeout => ("ERR_INACTIVITY_TIMEOUT", ErrorCategory::Transport),
+            Error::Version => ("ERR_PROTOCOL_VERSION", ErrorCategory::Transport),
+            Error::PacketAuth | Error::DecryptionError => {
+                ("ERR_PACKET_INTEGRITY", ErrorCategory::Transport)
+            }
+            _ => ("ERR_PROTOCOL", ErrorCategory::Transport),
+        };
+        Self::new(code, category, err)
+    }
+
+    fn from_keys(err: &russh_keys::Error, category: ErrorCategory) -> Self {
+        use russh_keys::Error;
+        let (code, category) = match err {
+            // Keys are parsed from memory, so I/O only happens when talking to an agent
+            Error::IO(err) => return Self::from_io(err, ErrorCategory::Agent),
+            Error::KeyIsEncrypted => ("ERR_KEY_ENCRYPTED", category),
+            Error::UnsupportedKeyType { .. } | Error::UnknownAlgorithm(_) => {
+                ("ERR_UNSUPPORTED_KEY_TYPE", category)
+            }
+            Error::AgentProtocolError
+            | Error::AgentFailure
+            | Error::BadAuthSock
+            | Error::EnvVar(_) => ("ERR_AGENT_FAILURE", ErrorCategory::Agent),
+            #[cfg(windows)]
+            Error::Pageant(_) => ("ERR_AGENT_FAILURE", ErrorCategory::Agent),
+            _ => ("ERR_INVALID_KEY", category),
+        };
+        Self::new(code, category, err)
+    }
+
+    fn from_sftp(err: &russh_sftp::client::error::Error) -> Self {
+        use russh_sftp::client::error::Error;
+        match err {
+            Error::Status(status) => {
+                let code = match status.status_code {
+                    StatusCode::Eof => "ERR_SFTP_EOF",
+                    StatusCode::NoSuchFile => "ERR_SFTP_NO_SUCH_FILE",
+                    StatusCode::PermissionDenied => "ERR_SFTP_PERMISSION_DENIED",
+                    StatusCode::OpUnsupported => "ERR_SFTP_UNSUPPORTED",
+                    StatusCode::NoConnection | StatusCode::ConnectionLost => "ERR_CONNECTION_LOST",
+                    _ => "ERR_SFTP_FAILURE",
+                };
+                Self {
+                    sftp_status: Some(status.status_code as u32),
+                    ..Self::new(code, ErrorCategory::Sftp, err)
+                }
+            }
+            Error::IO(_) => Self::new("ERR_IO", ErrorCategory::Sftp, err),
+            Error::Timeout => Self::new("ERR_TIMEOUT", ErrorCategory::Sftp, err),
+            _ => Self::new("ERR_SFTP_PROTOCOL", ErrorCategory::Sftp, err),
+        }
+    }
+
+    fn from_socks(err: &tokio_socks::Error) -> Self {
+        use tokio_socks::Error;
+        let code = match err {
+            Error::Io(err) => return Self::from_io(err, ErrorCategory::Proxy),
+            Error::ProxyServerUnreachable => "ERR_PROXY_UNREACHABLE",
+            Error::NoAcceptableAuthMethods
+            | Error::UnknownAuthMethod
+            | Error::InvalidAuthValues(_)
+            | Error::PasswordAuthFailure(_)
+            | Error::AuthorizationRequired
+            | Error::IdentdAuthFailure
+            | Error::InvalidUserIdAuthFailure => "ERR_PROXY_AUTH_FAILED",
+            Error::ConnectionNotAllowedByRuleset
+            | Error::NetworkUnreachable
+            | Error::HostUnreachable
+            | Error::ConnectionRefused
+            | Error::TtlExpired => "ERR_PROXY_TARGET_UNREACHABLE",
+            _ => "ERR_PROXY_PROTOCOL",
+        };
+        Self::new(code, ErrorCategory::Proxy, err)
+    }
+
+    fn from_http(err: &async_http_proxy::HttpError) -> Self {
+        use async_http_proxy::HttpError;
+        let code = match err {
+            HttpError::IoError(err) => return Self::from_io(err, ErrorCategory::Proxy),
+            HttpError::HttpCode200(407) => "ERR_PROXY_AUTH_FAILED",
+            HttpError::HttpCode200(_) => "ERR_PROXY_TARGET_UNREACHABLE",
+            _ => "ERR_PROXY_PROTOCOL",
+        };
+        Self::new(code, ErrorCategory::Proxy, err)
+    }
 }
 
-fn to_napi_err<E: std::fmt::Debug>(err: E) -> napi::Error {
-    napi::Error::new(napi::Status::GenericFailure, format!("{err:?}"))
+impl From<ErrorDetails> for WrappedError {
+    fn from(details: ErrorDetails) -> Self {
+        WrappedError::Details(details)
+    }
+}
+
+impl From<ErrorDetails> for napi::Error {
+    fn from(details: ErrorDetails) -> Self {
+        let reason = serde_json::to_string(&details).unwrap_or(details.message);
+        napi::Error::new(napi::Status::GenericFailure, reason)
+    }
 }
 
 impl From<WrappedError> for napi::Error {
     fn from(value: WrappedError) -> Self {
         match value {
-            WrappedError::Io(err) => to_napi_err(err),
-            WrappedError::Russh(err) => to_napi_err(err),
-            WrappedError::RusshKeys(err) => to_napi_err(err),
-            WrappedError::AgentAuthError(err) => to_napi_err(err),
-            WrappedError::Sftp(err) => to_napi_err(err),
-            WrappedError::Socks(err) => to_napi_err(err),
-            WrappedError::Http(err) => to_napi_err(err),
+            WrappedError::Io(err) => ErrorDetails::from_io(&err, ErrorCategory::Transport).into(),
+            WrappedError::Russh(err) => ErrorDetails::from_russh(&err).into(),
+            WrappedError::RusshKeys(err) => {
+                ErrorDetails::from_keys(&err, ErrorCategory::Key).into()
+            }
+            WrappedError::AgentAuthError(AgentAuthError::Key(err)) => {
+                ErrorDetails::from_keys(&err, ErrorCategory::Agent).into()
+            }
+            WrappedError::AgentAuthError(err) => {
+                ErrorDetails::new("ERR_DISCONNECTED", ErrorCategory::Transport, err).into()
+            }
+            WrappedError::Sftp(err) => ErrorDetails::from_sftp(&err).into(),
+            WrappedError::Socks(err) => ErrorDetails::from_socks(&err).into(),
+            WrappedError::Http(err) => ErrorDetails::from_http(&err).into(),
             WrappedError::Node(err) => err,
+            WrappedError::Details(details) => details.into(),
         }
     }
 }
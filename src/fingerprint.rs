@@ -0,0 +1,128 @@
+use data_encoding::{BASE64_NOPAD, HEXLOWER};
+use napi_derive::napi;
+use sha2::{Digest, Sha256};
+use ssh_key::public::KeyData;
+use ssh_key::EcdsaCurve;
+
+#[napi(string_enum = "lowercase")]
+pub enum FingerprintHash {
+    /// `SHA256:` followed by unpadded base64.
+    Sha256,
+    /// `MD5:` followed by colon-separated hex pairs.
+    Md5,
+}
+
+impl FingerprintHash {
+    fn digest(&self, blob: &[u8]) -> Vec<u8> {
+        match self {
+            FingerprintHash::Sha256 => Sha256::digest(blob).to_vec(),
+            FingerprintHash::Md5 => md5::compute(blob).to_vec(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            FingerprintHash::Sha256 => "SHA256",
+            FingerprintHash::Md5 => "MD5",
+        }
+    }
+}
+
+/// Formats the fingerprint of a public key blob as `ssh-keygen -l` does.
+pub(crate) fn fingerprint(blob: &[u8], hash: &FingerprintHash) -> String {
+    let digest = hash.digest(blob);
+    let value = match hash {
+        FingerprintHash::Sha256 => BASE64_NOPAD.encode(&digest),
+        FingerprintHash::Md5 => digest
+            .iter()
+            .map(|x| HEXLOWER.encode(&[*x]))
+            .collect::<Vec<_>>()
+            .join(":"),
+    };
+    format!("{}:{value}", hash.name())
+}
+
+/// Key type as shown by `ssh-keygen`, e.g. `ED25519`.
+pub(crate) fn key_type_name(key: &KeyData) -> &'static str {
+    match key {
+        KeyData::Dsa(_) => "DSA",
+        KeyData::Ecdsa(_) => "ECDSA",
+        KeyData::Ed25519(_) => "ED25519",
+        KeyData::Rsa(_) => "RSA",
+        KeyData::SkEcdsaSha2NistP256(_) => "ECDSA-SK",
+        KeyData::SkEd25519(_) => "ED25519-SK",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Modulus size for RSA and DSA, curve size otherwise.
+pub(crate) fn key_bits(key: &KeyData) -> u32 {
+    fn mpint_bits(value: &ssh_key::Mpint) -> u32 {
+        let bytes = value.as_positive_bytes().unwrap_or_default();
+        match bytes.first() {
+            Some(first) => bytes.len() as u32 * 8 - first.leading_zeros(),
+            None => 0,
+        }
+    }
+    match key {
+        KeyData::Dsa(key) => mpint_bits(&key.p),
+        KeyData::Rsa(key) => mpint_bits(&key.n),
+        KeyData::Ecdsa(key) => match key.curve() {
+            EcdsaCurve::NistP256 => 256,
+            EcdsaCurve::NistP384 => 384,
+            EcdsaCurve::NistP521 => 521,
+        },
+        KeyData::SkEcdsaSha2NistP256(_) => 256,
+        KeyData::Ed25519(_) | KeyData::SkEd25519(_) => 256,
+        _ => 0,
+    }
+}
+
+const WIDTH: usize = 17;
+const HEIGHT: usize = 9;
+const SYMBOLS: &[u8] = b" .o+=*BOX@%&#/^SE";
+
+/// OpenSSH's "drunken bishop" visualisation of the fingerprint, framed by
+/// `[type bits]` and the hash name.
+pub(crate) fn randomart(blob: &[u8], key: &KeyData, hash: &FingerprintHash) -> String {
+    let start = SYMBOLS.len() - 2;
+    let end = SYMBOLS.len() - 1;
+    let mut field = [[0usize; WIDTH]; HEIGHT];
+    let (mut x, mut y) = (WIDTH / 2, HEIGHT / 2);
+    for byte in hash.digest(blob) {
+        for step in 0..4 {
+            let bits = byte >> (step * 2);
+            x = if bits & 1 == 0 {
+                x.saturating_sub(1)
+            } else {
+                (x + 1).min(WIDTH - 1)
+            };
+            y = if bits & 2 == 0 {
+                y.saturating_sub(1)
+            } else {
+                (y + 1).min(HEIGHT - 1)
+            };
+            if field[y][x] < start - 1 {
+                field[y][x] += 1;
+            }
+        }
+    }
+    field[HEIGHT / 2][WIDTH / 2] = start;
+    field[y][x] = end;
+
+    // Like ssh-keygen, drop the size if the title doesn't fit
+    let name = key_type_name(key);
+    let mut title = format!("[{name} {}]", key_bits(key));
+    if title.len() > WIDTH {
+        title = format!("[{name}]");
+    }
+    title.truncate(WIDTH - 1);
+    let mut art = format!("+{title:-^WIDTH$}+\n");
+    for row in field {
+        art.push('|');
+        art.extend(row.iter().map(|x| SYMBOLS[*x] as char));
+        art.push_str("|\n");
+    }
+    art.push_str(&format!("+{:-^WIDTH$}+", format!("[{}]", hash.name())));
+    art
+}
@@ -0,0 +1,258 @@
+use std::convert::TryInto;
+use std::io::IoSlice;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use napi_derive::napi;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+const MSG_KEXINIT: u8 = 20;
+const STRICT_KEX_CLIENT: &str = "kex-strict-c-v00@openssh.com";
+const STRICT_KEX_SERVER: &str = "kex-strict-s-v00@openssh.com";
+const AEAD_CIPHERS: &[&str] = &["chacha20-poly1305@openssh.com", "aes256-gcm@openssh.com"];
+/// Give up on recording a direction if its KEXINIT hasn't shown up by then.
+const MAX_RECORDED_BYTES: usize = 256 * 1024;
+
+/// Details of the initial key exchange, as negotiated with the server.
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct ConnectionInfo {
+    /// The server's identification string, e.g. `SSH-2.0-OpenSSH_9.6`.
+    pub server_id: String,
+    pub client_id: String,
+    pub kex_algorithm: String,
+    pub host_key_algorithm: String,
+    pub cipher_client_to_server: String,
+    pub cipher_server_to_client: String,
+    /// `None` when the cipher is an AEAD cipher with an implicit MAC.
+    pub mac_client_to_server: Option<String>,
+    pub mac_server_to_client: Option<String>,
+    pub compression_client_to_server: String,
+    pub compression_server_to_client: String,
+    /// Whether both sides agreed on strict key exchange (the Terrapin mitigation).
+    pub strict_kex: bool,
+}
+
+/// Identification line and KEXINIT name-lists sent by one side.
+#[derive(Default)]
+struct SideRecording {
+    buffer: Vec<u8>,
+    id: Option<String>,
+    kexinit: Option<Vec<Vec<String>>>,
+}
+
+impl SideRecording {
+    fn is_done(&self) -> bool {
+        self.kexinit.is_some() || self.buffer.len() > MAX_RECORDED_BYTES
+    }
+
+    fn feed(&mut self, data: &[u8]) {
+        if self.is_done() {
+            return;
+        }
+        self.buffer.extend_from_slice(data);
+        if let Some((id, kexinit)) = parse_handshake(&self.buffer) {
+            self.id = Some(id);
+            self.kexinit = Some(kexinit);
+            self.buffer = vec![];
+        }
+    }
+}
+
+/// Parses the identification line and the first KEXINIT packet, returning
+/// `None` until enough data is available.
+fn parse_handshake(buffer: &[u8]) -> Option<(String, Vec<Vec<String>>)> {
+    // Servers may send other lines before the identification string
+    let mut rest = buffer;
+    let id = loop {
+        let eol = rest.iter().position(|x| *x == b'\n')?;
+        let line = String::from_utf8_lossy(&rest[..eol]);
+        rest = &rest[eol + 1..];
+        if line.starts_with("SSH-") {
+            break line.trim_end_matches('\r').to_string();
+        }
+    };
+
+    if rest.len() < 5 {
+        return None;
+    }
+    let packet_length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+    let padding_length = rest[4] as usize;
+    if rest.len() < 4 + packet_length {
+        return None;
+    }
+    let payload = rest.get(5..4 + packet_length.checked_sub(padding_length)?)?;
+    if payload.first() != Some(&MSG_KEXINIT) {
+        return None;
+    }
+
+    // Skip the message type and the 16-byte cookie
+    let mut payload = payload.get(17..)?;
+    let mut lists = vec![];
+    for _ in 0..10 {
+        let len = u32::from_be_bytes(payload.get(..4)?.try_into().ok()?) as usize;
+        let list = String::from_utf8_lossy(payload.get(4..4 + len)?);
+        lists.push(
+            list.split(',')
+                .filter(|x| !x.is_empty())
+                .map(Into::into)
+                .collect(),
+        );
+        payload = &payload[4 + len..];
+    }
+    Some((id, lists))
+}
+
+/// Shared between the recording stream and the client; filled in as the
+/// handshake passes through.
+#[derive(Clone, Default)]
+pub(crate) struct HandshakeRecorder(Arc<Mutex<(SideRecording, SideRecording)>>);
+
+impl HandshakeRecorder {
+    fn record_sent(&self, data: &[u8]) {
+        if let Ok(mut state) = self.0.lock() {
+            state.0.feed(data);
+        }
+    }
+
+    fn record_received(&self, data: &[u8]) {
+        if let Ok(mut state) = self.0.lock() {
+            state.1.feed(data);
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.0
+            .lock()
+            .map(|state| state.0.is_done() && state.1.is_done())
+            .unwrap_or(true)
+    }
+
+    /// Applies the RFC 4253 section 7.1 rule: the first algorithm on the
+    /// client's list that the server also supports wins.
+    pub(crate) fn connection_info(&self) -> Option<ConnectionInfo> {
+        let state = self.0.lock().ok()?;
+        let (client, server) = (&state.0, &state.1);
+        let (client_lists, server_lists) = (client.kexinit.as_ref()?, server.kexinit.as_ref()?);
+
+        let negotiate = |index: usize| -> String {
+            client_lists[index]
+                .iter()
+                .find(|x| server_lists[index].contains(x))
+                .cloned()
+                .unwrap_or_default()
+        };
+        let mac_for = |cipher: &str, index: usize| {
+            if AEAD_CIPHERS.contains(&cipher) {
+                None
+            } else {
+                Some(negotiate(index))
+            }
+        };
+
+        let cipher_client_to_server = negotiate(2);
+        let cipher_server_to_client = negotiate(3);
+        Some(ConnectionInfo {
+            server_id: server.id.clone()?,
+            client_id: client.id.clone()?,
+            kex_algorithm: negotiate(0),
+            host_key_algorithm: negotiate(1),
+            mac_client_to_server: mac_for(&cipher_client_to_server, 4),
+            mac_server_to_client: mac_for(&cipher_server_to_client, 5),
+            cipher_client_to_server,
+            cipher_server_to_client,
+            compression_client_to_server: negotiate(6),
+            compression_server_to_client: negotiate(7),
+            strict_kex: client_lists[0].iter().any(|x| x == STRICT_KEX_CLIENT)
+                && server_lists[0].iter().any(|x| x == STRICT_KEX_SERVER),
+        })
+    }
+}
+
+/// Passes the transport through unchanged while feeding the plaintext
+/// start of the session to a [HandshakeRecorder].
+pub(crate) struct RecordingStream<S> {
+    inner: S,
+    /// Dropped once the handshake is recorded, so that the rest of the
+    /// session passes through without locking.
+    recorder: Option<HandshakeRecorder>,
+}
+
+impl<S> RecordingStream<S> {
+    pub(crate) fn new(inner: S, recorder: HandshakeRecorder) -> Self {
+        Self {
+            inner,
+            recorder: Some(recorder),
+        }
+    }
+
+    fn record(&mut self, record: impl FnOnce(&HandshakeRecorder)) {
+        if let Some(recorder) = &self.recorder {
+            record(recorder);
+            if recorder.is_done() {
+                self.recorder = None;
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for RecordingStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        let this = self.get_mut();
+        let filled_before = buf.filled().len();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = result {
+            this.record(|x| x.record_received(&buf.filled()[filled_before..]));
+        }
+        result
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for RecordingStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = result {
+            this.record(|x| x.record_sent(&buf[..n]));
+        }
+        result
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        let this = self.get_mut();
+        if this.recorder.is_some() {
+            // Keep the recording in order by writing one slice at a time
+            let buf = bufs.iter().find(|b| !b.is_empty()).map_or(&[][..], |b| b);
+            return Pin::new(this).poll_write(cx, buf);
+        }
+        Pin::new(&mut this.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
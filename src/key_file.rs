@@ -0,0 +1,265 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use napi::bindgen_prelude::Promise;
+use napi::threadsafe_function::ThreadsafeFunction;
+use napi_derive::napi;
+use pkcs8::pkcs5::pbes2;
+use pkcs8::EncryptedPrivateKeyInfo;
+use ssh_key::Kdf;
+use zeroize::Zeroizing;
+
+use crate::certificate::public_key_from_data;
+use crate::error::{ErrorCategory, ErrorDetails, WrappedError};
+use crate::fingerprint::key_bits;
+use crate::key::{decode_key, public_key_from_blob, PrivateKeyFormat, SshKeyPair, SshPublicKey};
+use crate::openssh::OpensshFile;
+use crate::ppk;
+use crate::secret::{take_secret, Secret};
+
+/// What `keyInfo()` can tell about a private key without decrypting it.
+#[napi(object, object_from_js = false)]
+pub struct KeyInfo {
+    pub format: PrivateKeyFormat,
+    /// e.g. `ssh-ed25519`. `null` if the format encrypts it too.
+    pub key_type: Option<String>,
+    pub bits: Option<u32>,
+    /// Only OpenSSH and PuTTY keys have comments, and OpenSSH encrypts it.
+    pub comment: Option<String>,
+    pub public_key: Option<SshPublicKey>,
+    pub encrypted: bool,
+    /// Passphrase key derivation, e.g. `bcrypt`, `pbkdf2`, `argon2id` or,
+    /// for legacy PEM keys, `md5`.
+    pub kdf: Option<String>,
+    /// bcrypt rounds, PBKDF2 iterations or Argon2 passes.
+    pub kdf_rounds: Option<u32>,
+    /// e.g. `aes256-ctr` for OpenSSH keys or `aes-256-cbc` for PEM keys.
+    pub cipher: Option<String>,
+}
+
+impl KeyInfo {
+    fn new(format: PrivateKeyFormat) -> Self {
+        Self {
+            format,
+            key_type: None,
+            bits: None,
+            comment: None,
+            public_key: None,
+            encrypted: false,
+            kdf: None,
+            kdf_rounds: None,
+            cipher: None,
+        }
+    }
+
+    fn with_public_key(mut self, key: SshPublicKey) -> napi::Result<Self> {
+        let key_data = key.key_data()?;
+        self.key_type = Some(key_data.algorithm().as_str().into());
+        self.bits = Some(key_bits(&key_data));
+        self.public_key = Some(key);
+        Ok(self)
+    }
+}
+
+fn invalid_key(message: &str) -> napi::Error {
+    ErrorDetails::new("ERR_INVALID_KEY", ErrorCategory::Key, message).into()
+}
+
+fn pem_label(data: &str) -> Option<&str> {
+    data.lines()
+        .find_map(|x| x.trim().strip_prefix("-----BEGIN "))
+        .and_then(|x| x.strip_suffix("-----"))
+}
+
+fn pkcs8_cipher(scheme: &pbes2::EncryptionScheme) -> Option<&'static str> {
+    match scheme {
+        pbes2::EncryptionScheme::Aes128Cbc { .. } => Some("aes-128-cbc"),
+        pbes2::EncryptionScheme::Aes192Cbc { .. } => Some("aes-192-cbc"),
+        pbes2::EncryptionScheme::Aes256Cbc { .. } => Some("aes-256-cbc"),
+        _ => None,
+    }
+}
+
+fn encrypted_pkcs8_info(data: &str) -> napi::Result<KeyInfo> {
+    let (_, der) = sec1::der::pem::decode_vec(data.trim().as_bytes())
+        .map_err(|_| invalid_key("Invalid PEM encoding"))?;
+    let info = EncryptedPrivateKeyInfo::try_from(der.as_slice())
+        .map_err(|_| invalid_key("Invalid PKCS#8 encrypted key"))?;
+    let mut result = KeyInfo {
+        encrypted: true,
+        ..KeyInfo::new(PrivateKeyFormat::Pkcs8)
+    };
+    match info.encryption_algorithm.pbes2() {
+        Some(params) => {
+            if let Some(pbkdf2) = params.kdf.pbkdf2() {
+                result.kdf = Some("pbkdf2".into());
+                result.kdf_rounds = Some(pbkdf2.iteration_count);
+            } else if params.kdf.is_scrypt() {
+                result.kdf = Some("scrypt".into());
+            }
+            result.cipher = pkcs8_cipher(&params.encryption).map(Into::into);
+        }
+        // PBES1 derives the key with PBKDF1
+        None => result.kdf = Some("pbkdf1".into()),
+    }
+    Ok(result)
+}
+
+/// Legacy PEM keys name the cipher in a `DEK-Info` header and derive the
+/// key with OpenSSL's MD5-based `EVP_BytesToKey`.
+fn encrypted_pem_info(data: &str, label: &str) -> KeyInfo {
+    let cipher = data
+        .lines()
+        .find_map(|x| x.trim().strip_prefix("DEK-Info:"))
+        .and_then(|x| x.split(',').next())
+        .map(|x| x.trim().to_lowercase());
+    KeyInfo {
+        // The curve of EC keys is only in the encrypted part
+        key_type: (label == "RSA PRIVATE KEY").then(|| "ssh-rsa".into()),
+        encrypted: true,
+        kdf: Some("md5".into()),
+        kdf_rounds: Some(1),
+        cipher,
+        ..KeyInfo::new(PrivateKeyFormat::Pem)
+    }
+}
+
+/// Describes a private key in any format `parseKey()` accepts, without
+/// needing its passphrase. A `Uint8Array` is wiped once read.
+#[napi]
+pub fn key_info(data: Secret) -> napi::Result<KeyInfo> {
+    describe_key(&take_secret(data)?)
+}
+
+fn describe_key(data: &str) -> napi::Result<KeyInfo> {
+    if ppk::is_ppk(data) {
+        let info = ppk::ppk_info(data)?;
+        let (kdf, kdf_rounds) = match info.kdf {
+            Some((kdf, rounds)) => (Some(kdf.to_string()), rounds),
+            None => (None, None),
+        };
+        return KeyInfo {
+            comment: Some(info.comment).filter(|x| !x.is_empty()),
+            encrypted: info.encrypted,
+            kdf,
+            kdf_rounds,
+            cipher: info.encrypted.then(|| "aes256-cbc".into()),
+            ..KeyInfo::new(PrivateKeyFormat::Ppk)
+        }
+        .with_public_key(public_key_from_blob(&info.public)?);
+    }
+
+    let label = pem_label(data).ok_or_else(|| invalid_key("Unrecognised key format"))?;
+    let format = match label {
+        "OPENSSH PRIVATE KEY" => PrivateKeyFormat::Openssh,
+        "PRIVATE KEY" | "ENCRYPTED PRIVATE KEY" => PrivateKeyFormat::Pkcs8,
+        "RSA PRIVATE KEY" | "EC PRIVATE KEY" | "DSA PRIVATE KEY" => PrivateKeyFormat::Pem,
+        _ => return Err(invalid_key(&format!("Unrecognised PEM label `{label}`"))),
+    };
+
+    if let PrivateKeyFormat::Openssh = format {
+        let file = OpensshFile::parse(data)?;
+        let mut info = KeyInfo {
+            encrypted: file.is_encrypted(),
+            ..KeyInfo::new(PrivateKeyFormat::Openssh)
+        };
+        if file.is_encrypted() {
+            info.cipher = Some(file.cipher.as_str().into());
+        } else {
+            info.comment = Some(file.decode(None)?.comment().to_string()).filter(|x| !x.is_empty());
+        }
+        if let Kdf::Bcrypt { rounds, .. } = &file.kdf {
+            info.kdf = Some("bcrypt".into());
+            info.kdf_rounds = Some(*rounds);
+        }
+        return info.with_public_key(public_key_from_data(&file.public)?);
+    }
+    if label == "ENCRYPTED PRIVATE KEY" {
+        return encrypted_pkcs8_info(data);
+    }
+    if crate::key::is_encrypted_pem(data) {
+        return Ok(encrypted_pem_info(data, label));
+    }
+
+    let key = decode_key(data, None)?;
+    KeyInfo::new(format).with_public_key(key.public_key()?)
+}
+
+/// `~` and `~/...` relative to the home directory.
+fn expand_home(path: &str) -> String {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return path.to_string(),
+    };
+    let home = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE"));
+    match home {
+        Ok(home) => format!("{home}{rest}"),
+        Err(_) => path.to_string(),
+    }
+}
+
+/// Called with the expanded path and the attempt number, starting at 1.
+/// Resolves to the passphrase, or `null` to give up. A `Uint8Array` is
+/// wiped once tried.
+pub type PassphraseCallback = ThreadsafeFunction<(String, u32), Promise<Option<Secret>>>;
+
+/// Reads a private key file, asking `passphrase_callback` for a passphrase
+/// only if the key is encrypted. A wrong passphrase is asked for again, up
+/// to `max_attempts` times (default 3).
+///
+/// Fails with `ERR_NOT_FOUND` or another I/O code if the file can't be
+/// read, `ERR_INVALID_KEY` or `ERR_UNSUPPORTED_KEY_TYPE` if it can't be
+/// parsed, `ERR_KEY_ENCRYPTED` if no passphrase was given and
+/// `ERR_WRONG_PASSPHRASE` once the attempts run out.
+#[napi]
+pub async fn load_key_file(
+    path: String,
+    passphrase_callback: Option<PassphraseCallback>,
+    max_attempts: Option<u32>,
+) -> napi::Result<SshKeyPair> {
+    let path = expand_home(&path);
+    let data = Arc::new(Zeroizing::new(
+        tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| ErrorDetails::from_io(&e, ErrorCategory::Key))?,
+    ));
+
+    let decode = |passphrase: Option<Zeroizing<String>>| {
+        let data = data.clone();
+        async move {
+            tokio::task::spawn_blocking(move || {
+                decode_key(&data, passphrase.as_ref().map(|x| x.as_str()))
+            })
+            .await
+            .map_err(|e| WrappedError::from(ErrorDetails::from_join(&e, ErrorCategory::Key)))?
+        }
+    };
+
+    if !describe_key(&data)?.encrypted {
+        return Ok(decode(None).await?);
+    }
+    let no_passphrase = || -> napi::Error {
+        ErrorDetails::new(
+            "ERR_KEY_ENCRYPTED",
+            ErrorCategory::Key,
+            "The key is encrypted and no passphrase was given",
+        )
+        .into()
+    };
+    let callback = passphrase_callback.ok_or_else(no_passphrase)?;
+    let max_attempts = max_attempts.unwrap_or(3).max(1);
+    let mut attempt = 1;
+    loop {
+        let passphrase = callback
+            .call_async(Ok((path.clone(), attempt)))
+            .await?
+            .await?
+            .ok_or_else(no_passphrase)?;
+        match decode(Some(take_secret(passphrase)?)).await {
+            Err(e) if attempt < max_attempts && e.code() == Some("ERR_WRONG_PASSPHRASE") => {
+                attempt += 1;
+            }
+            result => return Ok(result?),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use napi_derive::napi;
+use regex::Regex;
+use russh::client::Prompt;
+use sha1::Sha1;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::error::{ErrorCategory, ErrorDetails};
+use crate::secret::{take_optional_secret, Secret};
+
+/// Answers keyboard-interactive prompts whose text matches `prompt` without
+/// asking JS. Set either `response` or `totp_secret`, which are wiped once
+/// read if given as a `Uint8Array`.
+#[napi(object, object_to_js = false)]
+pub struct KeyboardInteractiveRule {
+    /// Regular expression matched against the prompt text.
+    pub prompt: String,
+    /// Sent as-is.
+    pub response: Option<Secret>,
+    /// Base32 seed for an RFC 6238 TOTP code, as shown in authenticator
+    /// enrollment URIs.
+    pub totp_secret: Option<Secret>,
+    /// Defaults to 6.
+    pub totp_digits: Option<u32>,
+    /// Time step in seconds, defaults to 30.
+    pub totp_period: Option<u32>,
+}
+
+enum Answer {
+    Static(Zeroizing<String>),
+    Totp {
+        key: Zeroizing<Vec<u8>>,
+        digits: u32,
+        period: u64,
+    },
+}
+
+impl Answer {
+    fn get(&self) -> String {
+        match self {
+            Answer::Static(response) => response.to_string(),
+            Answer::Totp {
+                key,
+                digits,
+                period,
+            } => {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |x| x.as_secs());
+                totp(key, now / period, *digits)
+            }
+        }
+    }
+}
+
+/// HOTP (RFC 4226) over `counter`, which TOTP derives from the time.
+fn totp(key: &[u8], counter: u64, digits: u32) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[19] & 0x0f) as usize;
+    let code = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        code as u64 % 10u64.pow(digits),
+        width = digits as usize
+    )
+}
+
+fn invalid_rule(message: String) -> napi::Error {
+    ErrorDetails::new("ERR_INVALID_ARGUMENT", ErrorCategory::Argument, message).into()
+}
+
+struct Responder {
+    rules: Vec<(Regex, Answer)>,
+}
+
+impl Responder {
+    fn new(rules: Vec<KeyboardInteractiveRule>) -> napi::Result<Self> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let prompt = Regex::new(&rule.prompt)
+                    .map_err(|e| invalid_rule(format!("Invalid prompt pattern: {e}")))?;
+                let response = take_optional_secret(rule.response)?;
+                let totp_secret = take_optional_secret(rule.totp_secret)?;
+                let answer = match (response, totp_secret) {
+                    (Some(response), None) => Answer::Static(response),
+                    (None, Some(secret)) => {
+                        let secret = Zeroizing::new(
+                            secret
+                                .chars()
+                                .filter(|c| !c.is_whitespace() && *c != '=')
+                                .map(|c| c.to_ascii_uppercase())
+                                .collect::<String>(),
+                        );
+                        let key = BASE32_NOPAD
+                            .decode(secret.as_bytes())
+                            .map(Zeroizing::new)
+                            .map_err(|e| invalid_rule(format!("Invalid TOTP secret: {e}")))?;
+                        let digits = rule.totp_digits.unwrap_or(6);
+                        if !(1..=9).contains(&digits) {
+                            return Err(invalid_rule(format!("Invalid TOTP digits: {digits}")));
+                        }
+                        Answer::Totp {
+                            key,
+                            digits,
+                            period: rule.totp_period.unwrap_or(30).max(1).into(),
+                        }
+                    }
+                    _ => {
+                        return Err(invalid_rule(
+                            "Set exactly one of response and totpSecret".into(),
+                        ))
+                    }
+                };
+                Ok((prompt, answer))
+            })
+            .collect::<napi::Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    fn answer(&self, prompt: &Prompt) -> Option<String> {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(&prompt.prompt))
+            .map(|(_, answer)| answer.get())
+    }
+}
+
+/// Keyboard-interactive state kept between JS round-trips.
+#[derive(Default)]
+pub(crate) struct KeyboardInteractiveSession {
+    responder: Option<Responder>,
+    /// Answers for the current request, `None` where JS has to respond.
+    pending: Zeroizing<Vec<Option<String>>>,
+}
+
+impl KeyboardInteractiveSession {
+    pub(crate) fn start(
+        &mut self,
+        rules: Option<Vec<KeyboardInteractiveRule>>,
+    ) -> napi::Result<()> {
+        self.responder = rules.map(Responder::new).transpose()?;
+        self.pending.zeroize();
+        Ok(())
+    }
+
+    /// Answers what the rules cover. Returns `None` if there are no rules.
+    pub(crate) fn answer(&mut self, prompts: &[Prompt]) -> Option<Vec<Option<String>>> {
+        let responder = self.responder.as_ref()?;
+        Some(prompts.iter().map(|x| responder.answer(x)).collect())
+    }
+
+    /// Remembers automatic answers while the remaining prompts go to JS.
+    pub(crate) fn set_pending(&mut self, answers: Vec<Option<String>>) {
+        self.pending = Zeroizing::new(answers);
+    }
+
+    /// Slots the JS `responses` in between the automatic answers. russh
+    /// only takes plain strings, so they are moved out rather than copied.
+    pub(crate) fn merge(&mut self, responses: Vec<Zeroizing<String>>) -> Vec<String> {
+        let mut responses = responses.into_iter().map(|mut x| std::mem::take(&mut *x));
+        if self.pending.is_empty() {
+            return responses.collect();
+        }
+        std::mem::take(&mut self.pending)
+            .iter_mut()
+            .map(|x| x.take().or_else(|| responses.next()).unwrap_or_default())
+            .collect()
+    }
+}
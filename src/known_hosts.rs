@@ -0,0 +1,308 @@
+use data_encoding::BASE64;
+use hmac::{Hmac, Mac};
+use napi_derive::napi;
+use rand::RngCore;
+use sha1::Sha1;
+use ssh_key::certificate::CertType;
+use ssh_key::public::KeyData;
+use ssh_key::HashAlg;
+
+use crate::certificate::SshCertificate;
+use crate::error::WrappedError;
+use crate::key::SshPublicKey;
+use crate::line_file::LineFile;
+
+const HASH_MAGIC: &str = "|1|";
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum KnownHostStatus {
+    /// The host is known with this exact key.
+    Match,
+    /// The host is known with a different key of the same type.
+    Mismatch,
+    /// No entry for this host and key type.
+    Unknown,
+    /// The key is marked `@revoked` for this host.
+    Revoked,
+    /// The certificate is signed by a trusted CA, but is not a host
+    /// certificate, doesn't name this host, has expired or has a bad signature.
+    Invalid,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    CertAuthority,
+    Revoked,
+}
+
+struct Entry {
+    marker: Option<Marker>,
+    hosts: String,
+    key: Vec<u8>,
+}
+
+impl Entry {
+    fn key_data(&self) -> Option<KeyData> {
+        ssh_key::PublicKey::from_bytes(&self.key)
+            .ok()
+            .map(|x| x.key_data().clone())
+    }
+}
+
+fn parse_entry(text: &str) -> Option<Entry> {
+    let mut fields = text.split_whitespace().peekable();
+    if fields.peek()?.starts_with('#') {
+        return None;
+    }
+    let marker = match *fields.peek()? {
+        "@cert-authority" => Some(Marker::CertAuthority),
+        "@revoked" => Some(Marker::Revoked),
+        x if x.starts_with('@') => return None,
+        _ => None,
+    };
+    if marker.is_some() {
+        fields.next();
+    }
+    let hosts = fields.next()?.to_string();
+    let _key_type = fields.next()?;
+    let key = BASE64.decode(fields.next()?.as_bytes()).ok()?;
+    Some(Entry { marker, hosts, key })
+}
+
+/// The key type embedded at the start of an SSH public key blob.
+pub(crate) fn blob_key_type(blob: &[u8]) -> Option<&[u8]> {
+    let len = u32::from_be_bytes([*blob.first()?, *blob.get(1)?, *blob.get(2)?, *blob.get(3)?]);
+    blob.get(4..4 + len as usize)
+}
+
+/// Hosts on non-standard ports are recorded as `[host]:port`.
+fn host_string(host: &str, port: u32) -> String {
+    let host = host.to_lowercase();
+    if port == 22 {
+        host
+    } else {
+        format!("[{host}]:{port}")
+    }
+}
+
+fn hmac_sha1(salt: &[u8], host: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha1>::new_from_slice(salt).expect("HMAC accepts keys of any length");
+    mac.update(host.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hash_host(host: &str) -> String {
+    let mut salt = [0; 20];
+    rand::thread_rng().fill_bytes(&mut salt);
+    format!(
+        "{HASH_MAGIC}{}|{}",
+        BASE64.encode(&salt),
+        BASE64.encode(&hmac_sha1(&salt, host))
+    )
+}
+
+/// `*` and `?` wildcard matching, as in OpenSSH's `match_pattern()`.
+/// Backtracks only to the last `*`, which keeps it O(n·m) on patterns
+/// from untrusted files.
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Pattern position after the last `*`, and the text position it matched up to
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                star = Some((p, t));
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    star = Some((star_p, t));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p.min(pattern.len())..].iter().all(|&c| c == b'*')
+}
+
+/// Comma-separated wildcard patterns, where a `!pattern` match overrides
+/// any positive one, as in OpenSSH's `match_pattern_list()`.
+pub(crate) fn pattern_list_match(list: &str, text: &str) -> bool {
+    let mut matched = false;
+    for pattern in list.split(',') {
+        let (negated, pattern) = match pattern.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        if wildcard_match(pattern.as_bytes(), text.as_bytes()) {
+            if negated {
+                return false;
+            }
+            matched = true;
+        }
+    }
+    matched
+}
+
+fn hosts_match(hosts: &str, host: &str) -> bool {
+    if let Some(hashed) = hosts.strip_prefix(HASH_MAGIC) {
+        let Some((salt, hash)) = hashed.split_once('|') else {
+            return false;
+        };
+        return match (
+            BASE64.decode(salt.as_bytes()),
+            BASE64.decode(hash.as_bytes()),
+        ) {
+            (Ok(salt), Ok(hash)) => hmac_sha1(&salt, host) == hash,
+            _ => false,
+        };
+    }
+
+    pattern_list_match(&hosts.to_lowercase(), host)
+}
+
+/// An OpenSSH `known_hosts` file.
+#[napi]
+pub struct KnownHosts {
+    file: LineFile<Entry>,
+}
+
+#[napi]
+impl KnownHosts {
+    #[napi(factory)]
+    pub fn parse(content: String) -> Self {
+        Self {
+            file: LineFile::parse(&content, parse_entry),
+        }
+    }
+
+    /// Reads a `known_hosts` file. A missing file is treated as empty.
+    #[napi]
+    pub async fn load(path: String) -> napi::Result<KnownHosts> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(content) => Ok(Self::parse(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::parse(String::new())),
+            Err(e) => Err(WrappedError::from(e).into()),
+        }
+    }
+
+    #[napi]
+    pub async fn save(&self, path: String) -> napi::Result<()> {
+        tokio::fs::write(path, self.to_string())
+            .await
+            .map_err(WrappedError::from)?;
+        Ok(())
+    }
+
+    #[napi]
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        self.file.to_string()
+    }
+
+    /// Looks up `key` for `host` and `port`. Revocations take precedence,
+    /// and a different key only counts as a mismatch if it has the same type,
+    /// since servers usually offer several host keys.
+    #[napi]
+    pub fn check(&self, host: String, port: u32, key: &SshPublicKey) -> KnownHostStatus {
+        let host = host_string(&host, port);
+        let key = key.blob();
+        let key_type = blob_key_type(&key);
+
+        let mut status = KnownHostStatus::Unknown;
+        for entry in self.file.entries() {
+            if !hosts_match(&entry.hosts, &host) {
+                continue;
+            }
+            match entry.marker {
+                Some(Marker::Revoked) if entry.key == key => return KnownHostStatus::Revoked,
+                Some(_) => (),
+                None if entry.key == key => status = KnownHostStatus::Match,
+                None if status == KnownHostStatus::Unknown
+                    && blob_key_type(&entry.key) == key_type =>
+                {
+                    status = KnownHostStatus::Mismatch
+                }
+                None => (),
+            }
+        }
+        status
+    }
+
+    /// Checks a host certificate against the `@cert-authority` lines for
+    /// `host` and `port`. A certificate from an unlisted CA is `unknown`.
+    /// Connections don't negotiate host certificates, so the certificate has
+    /// to come from elsewhere, e.g. `SshCertificate.parse()`.
+    #[napi]
+    pub fn check_certificate(
+        &self,
+        host: String,
+        port: u32,
+        certificate: &SshCertificate,
+    ) -> KnownHostStatus {
+        let cert = &certificate.inner;
+        let host = host.to_lowercase();
+        let entries = self
+            .file
+            .entries()
+            .filter(|x| hosts_match(&x.hosts, &host_string(&host, port)))
+            .collect::<Vec<_>>();
+
+        let revoked = entries
+            .iter()
+            .filter(|x| x.marker == Some(Marker::Revoked))
+            .filter_map(|x| x.key_data())
+            .any(|x| &x == cert.public_key() || &x == cert.signature_key());
+        if revoked {
+            return KnownHostStatus::Revoked;
+        }
+
+        let trusted_ca = entries
+            .iter()
+            .filter(|x| x.marker == Some(Marker::CertAuthority))
+            .filter_map(|x| x.key_data())
+            .find(|x| x == cert.signature_key());
+        let Some(ca) = trusted_ca else {
+            return KnownHostStatus::Unknown;
+        };
+
+        let principals = cert.valid_principals();
+        if cert.cert_type() != CertType::Host
+            || !(principals.is_empty() || principals.contains(&host))
+        {
+            return KnownHostStatus::Invalid;
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |x| x.as_secs());
+        match cert.validate_at(now, [&ca.fingerprint(HashAlg::Sha256)]) {
+            Ok(()) => KnownHostStatus::Match,
+            Err(_) => KnownHostStatus::Invalid,
+        }
+    }
+
+    /// Appends an entry for `host` and `port`, with the hostname hashed
+    /// if `hash` is set.
+    #[napi]
+    pub fn add(&mut self, host: String, port: u32, key: &SshPublicKey, hash: Option<bool>) {
+        let host = host_string(&host, port);
+        let hosts = if hash.unwrap_or(false) {
+            hash_host(&host)
+        } else {
+            host
+        };
+        let blob = key.blob();
+        let key_type = String::from_utf8_lossy(blob_key_type(&blob).unwrap_or_default());
+        let line = format!("{hosts} {key_type} {}", BASE64.encode(&blob));
+
+        self.file.push(&line);
+    }
+}
+
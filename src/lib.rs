@@ -1,83 +1,88 @@
-use std::convert::TryFrom;
 use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
 use channel::SshChannel;
-use key::{SshKeyPair, SshPublicKey};
-use napi::bindgen_prelude::{Promise, Uint8Array};
-use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use config::{ClientHandlerCallbacks, ConnectOptions};
+use key::{public_key_with_rsa_hash, RsaSignatureHash, SshKeyPair, SshPublicKey};
+use napi::bindgen_prelude::Object;
+use napi::threadsafe_function::ThreadsafeFunctionCallMode;
+use napi::{Env, JsObject};
 use napi_derive::napi;
 use russh::client::DisconnectReason;
 use russh::ChannelId;
 use russh_sftp::client::SftpSession;
 use sftp::SftpChannel;
+use signer::{JsSigner, SignCallback};
 use tokio::sync::Mutex;
 
-use error::WrappedError;
+use disconnect::{DisconnectReasonCode, LocalDisconnect};
+use error::{ErrorCategory, ErrorDetails, WrappedError};
+use handshake::{ConnectionInfo, HandshakeRecorder, RecordingStream};
+use keyboard_interactive::{KeyboardInteractiveRule, KeyboardInteractiveSession};
+use secret::{take_secret, Secret};
 
 mod agent;
+mod agent_client;
+mod algorithms;
+mod authorized_keys;
+mod certificate;
 mod channel;
+mod config;
+mod disconnect;
 mod error;
+mod fingerprint;
+mod handshake;
 mod key;
+mod key_file;
+mod keyboard_interactive;
+mod known_hosts;
+mod legacy_pem;
+mod line_file;
+mod openssh;
+mod ppk;
+mod secret;
 mod sftp;
+mod signer;
+mod sshsig;
 mod transport;
 
 pub use agent::*;
+pub use agent_client::*;
+pub use algorithms::*;
+pub use authorized_keys::*;
+pub use certificate::*;
+pub use key::generate_key;
 pub use key::is_pageant_running;
 pub use key::parse_key;
+pub use key::parse_public_key;
+pub use key_file::*;
+pub use known_hosts::*;
+pub use sshsig::*;
 use transport::SshTransport;
 
 pub struct SSHClientHandler {
-    pub server_key_callback: ThreadsafeFunction<SshPublicKey, Promise<bool>>,
-    pub data_callback: ThreadsafeFunction<(u32, Uint8Array)>,
-    pub extended_data_callback: ThreadsafeFunction<(u32, u32, Uint8Array)>,
-    pub eof_callback: ThreadsafeFunction<u32>,
-    pub close_callback: ThreadsafeFunction<u32>,
-    pub disconnect_callback: ThreadsafeFunction<Option<napi::Error>>,
-    pub x11_channel_open_callback: ThreadsafeFunction<(SshChannel, String, u32)>,
-    pub tcpip_channel_open_callback: ThreadsafeFunction<(SshChannel, String, u32, String, u32)>,
-    pub agent_channel_open_callback: ThreadsafeFunction<SshChannel>,
-    pub banner_callback: ThreadsafeFunction<String>,
+    callbacks: ClientHandlerCallbacks,
+    local_disconnect: LocalDisconnect,
 }
 
-#[napi]
-pub fn supported_ciphers() -> Vec<String> {
-    russh::cipher::ALL_CIPHERS
-        .iter()
-        .map(|x| x.as_ref().to_string())
-        .collect()
-}
-#[napi]
-pub fn supported_kex_algorithms() -> Vec<String> {
-    russh::kex::ALL_KEX_ALGORITHMS
-        .iter()
-        .map(|x| x.as_ref().to_string())
-        .collect()
-}
-
-#[napi]
-pub fn supported_macs() -> Vec<String> {
-    russh::mac::ALL_MAC_ALGORITHMS
-        .iter()
-        .map(|x| x.as_ref().to_string())
-        .collect()
-}
-
-#[napi]
-pub fn supported_compression_algorithms() -> Vec<String> {
-    russh::compression::ALL_COMPRESSION_ALGORITHMS
-        .iter()
-        .map(|x| x.as_ref().to_string())
-        .collect()
-}
-
-#[napi]
-pub fn supported_key_types() -> Vec<String> {
-    russh_keys::key::ALL_KEY_TYPES
-        .iter()
-        .map(|x| x.as_ref().to_string())
-        .collect()
+fn signal_name(signal: &russh::Sig) -> String {
+    match signal {
+        russh::Sig::ABRT => "ABRT",
+        russh::Sig::ALRM => "ALRM",
+        russh::Sig::FPE => "FPE",
+        russh::Sig::HUP => "HUP",
+        russh::Sig::ILL => "ILL",
+        russh::Sig::INT => "INT",
+        russh::Sig::KILL => "KILL",
+        russh::Sig::PIPE => "PIPE",
+        russh::Sig::QUIT => "QUIT",
+        russh::Sig::SEGV => "SEGV",
+        russh::Sig::TERM => "TERM",
+        russh::Sig::USR1 => "USR1",
+        russh::Sig::Custom(name) => name,
+    }
+    .into()
 }
 
 #[async_trait]
@@ -89,6 +94,7 @@ impl russh::client::Handler for SSHClientHandler {
         server_public_key: &russh_keys::key::PublicKey,
     ) -> Result<bool, Self::Error> {
         let response = self
+            .callbacks
             .server_key_callback
             .call_async(Ok(SshPublicKey::from(server_public_key.clone())))
             .await?
@@ -103,7 +109,7 @@ impl russh::client::Handler for SSHClientHandler {
         data: &[u8],
         _session: &mut russh::client::Session,
     ) -> Result<(), Self::Error> {
-        self.data_callback.call(
+        self.callbacks.data_callback.call(
             Ok((channel.into(), data.into())),
             ThreadsafeFunctionCallMode::NonBlocking,
         );
@@ -117,7 +123,7 @@ impl russh::client::Handler for SSHClientHandler {
         data: &[u8],
         _session: &mut russh::client::Session,
     ) -> Result<(), Self::Error> {
-        self.extended_data_callback.call(
+        self.callbacks.extended_data_callback.call(
             Ok((channel.into(), ext, data.into())),
             ThreadsafeFunctionCallMode::NonBlocking,
         );
@@ -129,7 +135,8 @@ impl russh::client::Handler for SSHClientHandler {
         channel: ChannelId,
         _session: &mut russh::client::Session,
     ) -> Result<(), Self::Error> {
-        self.eof_callback
+        self.callbacks
+            .eof_callback
             .call(Ok(channel.into()), ThreadsafeFunctionCallMode::NonBlocking);
         Ok(())
     }
@@ -139,20 +146,53 @@ impl russh::client::Handler for SSHClientHandler {
         channel: ChannelId,
         _session: &mut russh::client::Session,
     ) -> Result<(), Self::Error> {
-        self.close_callback
+        self.callbacks
+            .close_callback
             .call(Ok(channel.into()), ThreadsafeFunctionCallMode::NonBlocking);
         Ok(())
     }
 
+    async fn exit_status(
+        &mut self,
+        channel: ChannelId,
+        exit_status: u32,
+        _session: &mut russh::client::Session,
+    ) -> Result<(), Self::Error> {
+        self.callbacks.exit_status_callback.call(
+            Ok((channel.into(), exit_status)),
+            ThreadsafeFunctionCallMode::NonBlocking,
+        );
+        Ok(())
+    }
+
+    async fn exit_signal(
+        &mut self,
+        channel: ChannelId,
+        signal: russh::Sig,
+        core_dumped: bool,
+        error_message: &str,
+        lang_tag: &str,
+        _session: &mut russh::client::Session,
+    ) -> Result<(), Self::Error> {
+        self.callbacks.exit_signal_callback.call(
+            Ok((
+                channel.into(),
+                signal_name(&signal),
+                core_dumped,
+                error_message.into(),
+                lang_tag.into(),
+            )),
+            ThreadsafeFunctionCallMode::NonBlocking,
+        );
+        Ok(())
+    }
+
     async fn disconnected(
         &mut self,
         reason: DisconnectReason<Self::Error>,
     ) -> Result<(), Self::Error> {
-        self.disconnect_callback.call(
-            Ok(match reason {
-                DisconnectReason::Error(e) => Some(e.into()),
-                DisconnectReason::ReceivedDisconnect(_) => None,
-            }),
+        self.callbacks.disconnect_callback.call(
+            Ok(self.local_disconnect.to_event(reason)),
             ThreadsafeFunctionCallMode::NonBlocking,
         );
         Ok(())
@@ -165,7 +205,7 @@ impl russh::client::Handler for SSHClientHandler {
         originator_port: u32,
         _session: &mut russh::client::Session,
     ) -> Result<(), Self::Error> {
-        self.x11_channel_open_callback.call(
+        self.callbacks.x11_channel_open_callback.call(
             Ok((channel.into(), originator_address.into(), originator_port)),
             ThreadsafeFunctionCallMode::NonBlocking,
         );
@@ -181,7 +221,7 @@ impl russh::client::Handler for SSHClientHandler {
         originator_port: u32,
         _session: &mut russh::client::Session,
     ) -> Result<(), Self::Error> {
-        self.tcpip_channel_open_callback.call(
+        self.callbacks.tcpip_channel_open_callback.call(
             Ok((
                 channel.into(),
                 connected_address.into(),
@@ -199,7 +239,8 @@ impl russh::client::Handler for SSHClientHandler {
         channel: russh::Channel<russh::client::Msg>,
         _session: &mut russh::client::Session,
     ) -> Result<(), Self::Error> {
-        self.agent_channel_open_callback
+        self.callbacks
+            .agent_channel_open_callback
             .call(Ok(channel.into()), ThreadsafeFunctionCallMode::NonBlocking);
         Ok(())
     }
@@ -209,7 +250,8 @@ impl russh::client::Handler for SSHClientHandler {
         banner: &str,
         _session: &mut russh::client::Session,
     ) -> Result<(), Self::Error> {
-        self.banner_callback
+        self.callbacks
+            .banner_callback
             .call(Ok(banner.into()), ThreadsafeFunctionCallMode::NonBlocking);
         Ok(())
     }
@@ -285,94 +327,288 @@ impl From<russh::client::KeyboardInteractiveAuthResponse>
 #[napi]
 pub struct SshClient {
     handle: Arc<Mutex<russh::client::Handle<SSHClientHandler>>>,
+    handshake: HandshakeRecorder,
+    local_disconnect: LocalDisconnect,
+    keyboard_interactive: std::sync::Mutex<KeyboardInteractiveSession>,
+}
+
+/// Info requests answered by rules in a row before the next one goes to JS.
+const MAX_AUTO_ANSWERED_ROUNDS: usize = 8;
+
+impl SshClient {
+    fn keyboard_interactive(&self) -> std::sync::MutexGuard<'_, KeyboardInteractiveSession> {
+        self.keyboard_interactive
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Answers info requests the rules fully cover and returns the first
+    /// one that needs JS, with only the unanswered prompts. A prompt set
+    /// that comes back after being answered automatically, which usually
+    /// means a wrong rule, goes to JS in full, as does anything past
+    /// `MAX_AUTO_ANSWERED_ROUNDS`.
+    async fn continue_keyboard_interactive(
+        &self,
+        handle: &mut russh::client::Handle<SSHClientHandler>,
+        mut response: russh::client::KeyboardInteractiveAuthResponse,
+    ) -> napi::Result<KeyboardInteractiveAuthenticationState> {
+        let mut answered: Vec<Vec<String>> = vec![];
+        loop {
+            let russh::client::KeyboardInteractiveAuthResponse::InfoRequest {
+                name,
+                instructions,
+                prompts,
+            } = response
+            else {
+                return Ok(response.into());
+            };
+            let prompt_texts = prompts.iter().map(|x| x.prompt.clone()).collect::<Vec<_>>();
+            let repeated =
+                answered.contains(&prompt_texts) || answered.len() >= MAX_AUTO_ANSWERED_ROUNDS;
+            let answers = if repeated {
+                None
+            } else {
+                self.keyboard_interactive().answer(&prompts)
+            };
+            let Some(answers) = answers else {
+                return Ok(
+                    russh::client::KeyboardInteractiveAuthResponse::InfoRequest {
+                        name,
+                        instructions,
+                        prompts,
+                    }
+                    .into(),
+                );
+            };
+            if answers.iter().all(Option::is_some) {
+                answered.push(prompt_texts);
+                response = handle
+                    .authenticate_keyboard_interactive_respond(
+                        answers.into_iter().flatten().collect(),
+                    )
+                    .await
+                    .map_err(WrappedError::from)?;
+                continue;
+            }
+            let prompts = prompts
+                .into_iter()
+                .zip(&answers)
+                .filter(|(_, answer)| answer.is_none())
+                .map(|(prompt, _)| prompt)
+                .collect();
+            self.keyboard_interactive().set_pending(answers);
+            return Ok(
+                russh::client::KeyboardInteractiveAuthResponse::InfoRequest {
+                    name,
+                    instructions,
+                    prompts,
+                }
+                .into(),
+            );
+        }
+    }
 }
 
 #[napi]
 impl SshClient {
+    /// Server identification and the algorithms negotiated in the initial
+    /// key exchange. `null` until both sides have sent their KEXINIT, which
+    /// is guaranteed once the server key has been checked.
+    #[napi]
+    pub fn connection_info(&self) -> Option<ConnectionInfo> {
+        self.handshake.connection_info()
+    }
+
+    /// Tries the `none` method, which succeeds only on servers that don't
+    /// require authentication for this user.
+    ///
+    /// This and the other `authenticate_*` methods only report success or
+    /// failure. russh keeps the server's list of remaining methods and the
+    /// partial success flag to itself, so multi-factor logins have to try
+    /// each configured method in turn.
+    #[napi]
+    pub async fn authenticate_none(&self, username: String) -> napi::Result<bool> {
+        let mut handle = self.handle.lock().await;
+        handle
+            .authenticate_none(username)
+            .await
+            .map_err(WrappedError::from)
+            .map_err(Into::into)
+    }
+
+    /// russh ignores SSH_MSG_USERAUTH_PASSWD_CHANGEREQ, so if the server
+    /// asks for a password change, this only settles once the server gives up
+    /// on the login (e.g. after OpenSSH's `LoginGraceTime`).
+    ///
+    /// A `Uint8Array` password is wiped once read. russh keeps a plain copy
+    /// of its own until the request is sent.
     #[napi]
     pub async fn authenticate_password(
         &self,
         username: String,
-        password: String,
+        password: Secret,
     ) -> napi::Result<bool> {
+        let password = take_secret(password)?;
         let mut handle = self.handle.lock().await;
         handle
-            .authenticate_password(username, password)
+            .authenticate_password(username, password.as_str())
             .await
             .map_err(WrappedError::from)
             .map_err(Into::into)
     }
 
+    /// `rsa_hash` overrides the signature algorithm of RSA keys.
     #[napi]
     pub async fn authenticate_publickey(
         &self,
         username: String,
         key: &SshKeyPair,
+        rsa_hash: Option<RsaSignatureHash>,
     ) -> napi::Result<bool> {
         let mut handle = self.handle.lock().await;
         handle
-            .authenticate_publickey(username, Arc::new(key.inner.clone()))
+            .authenticate_publickey(username, Arc::new(key.with_rsa_hash(rsa_hash)?))
             .await
             .map_err(WrappedError::from)
             .map_err(Into::into)
     }
 
+    /// Authenticates with an OpenSSH user certificate and its private key,
+    /// using the certificate's `*-cert-v01@openssh.com` algorithm.
     #[napi]
-    pub async fn start_keyboard_interactive_authentication(
+    pub async fn authenticate_certificate(
         &self,
         username: String,
-    ) -> napi::Result<KeyboardInteractiveAuthenticationState> {
+        key: &SshKeyPair,
+        certificate: &SshCertificate,
+    ) -> napi::Result<bool> {
+        if !certificate.certifies(&key.public_key()?) {
+            return Err(ErrorDetails::new(
+                "ERR_CERTIFICATE_KEY_MISMATCH",
+                ErrorCategory::Auth,
+                "The certificate was not issued for this key",
+            )
+            .into());
+        }
         let mut handle = self.handle.lock().await;
         handle
-            .authenticate_keyboard_interactive_start(username, None)
+            .authenticate_openssh_cert(
+                username,
+                Arc::new(key.key_pair()?),
+                certificate.inner.clone(),
+            )
             .await
             .map_err(WrappedError::from)
             .map_err(Into::into)
-            .map(Into::into)
     }
 
+    /// `submethods` is passed to the server as-is, e.g. `pam`. Prompts
+    /// matching one of `rules` are answered without a JS round-trip; the
+    /// returned state only lists the prompts left for JS.
+    #[napi]
+    pub async fn start_keyboard_interactive_authentication(
+        &self,
+        username: String,
+        submethods: Option<String>,
+        rules: Option<Vec<KeyboardInteractiveRule>>,
+    ) -> napi::Result<KeyboardInteractiveAuthenticationState> {
+        self.keyboard_interactive().start(rules)?;
+        let mut handle = self.handle.lock().await;
+        let response = handle
+            .authenticate_keyboard_interactive_start(username, submethods)
+            .await
+            .map_err(WrappedError::from)?;
+        self.continue_keyboard_interactive(&mut handle, response)
+            .await
+    }
+
+    /// `Uint8Array` responses are wiped once read.
     #[napi]
     pub async fn respond_to_keyboard_interactive_authentication(
         &self,
-        responses: Vec<String>,
+        responses: Vec<Secret>,
     ) -> napi::Result<KeyboardInteractiveAuthenticationState> {
+        let responses = responses
+            .into_iter()
+            .map(take_secret)
+            .collect::<napi::Result<_>>()?;
+        let responses = self.keyboard_interactive().merge(responses);
         let mut handle = self.handle.lock().await;
-        handle
+        let response = handle
             .authenticate_keyboard_interactive_respond(responses)
             .await
-            .map_err(WrappedError::from)
-            .map_err(Into::into)
-            .map(|x| {
-                dbg!(&x);
-                x
-            })
-            .map(Into::into)
+            .map_err(WrappedError::from)?;
+        self.continue_keyboard_interactive(&mut handle, response)
+            .await
     }
 
-    #[napi]
+    /// Only the agent's plain keys are offered. Certificates held by the
+    /// agent are skipped, as russh can only sign a certificate login with a
+    /// local private key; use `authenticate_certificate` for those.
+    #[napi(
+        ts_args_type = "username: string, connection: AgentConnection, options?: AgentAuthOptions | undefined | null"
+    )]
     pub async fn authenticate_agent(
         &self,
         username: String,
         connection: &AgentConnection,
-    ) -> napi::Result<bool> {
+        options: Option<AgentAuthOptions>,
+    ) -> napi::Result<AgentAuthResult> {
         let mut handle = self.handle.lock().await;
 
         let mut agent = get_agent_client(connection).await?;
 
-        let keys = agent
-            .request_identities()
-            .await
-            .map_err(WrappedError::from)?;
+        // russh's own listing fails outright on any certificate in the agent
+        let keys = SshAgentClient::new(connection)
+            .list_identities()
+            .await?
+            .into_iter()
+            .map(|x| x.inner)
+            .collect();
+        let keys = options.unwrap_or_default().select(keys).await?;
+
+        let mut attempts = 0;
         for key in keys {
-            let (_agent, result) = handle.authenticate_future(&username, key, agent).await;
+            attempts += 1;
+            let (_agent, result) = handle
+                .authenticate_future(&username, key.clone(), agent)
+                .await;
             agent = _agent;
             let ret = result.map_err(|e| napi::Error::from(WrappedError::from(e)))?;
             if ret {
-                return Ok(true);
+                return Ok(AgentAuthResult {
+                    success: true,
+                    key: Some(key.into()),
+                    attempts,
+                });
             }
         }
 
-        Ok(false)
+        Ok(AgentAuthResult {
+            success: false,
+            key: None,
+            attempts,
+        })
+    }
+
+    /// Public key authentication where the signature is produced by `signer`,
+    /// e.g. a key held in an OS keychain or a remote KMS.
+    #[napi(
+        ts_args_type = "username: string, key: SshPublicKey, signer: (err: Error | null, data: Uint8Array, algorithm: string) => Promise<Uint8Array>, rsaHash?: RsaSignatureHash | undefined | null"
+    )]
+    pub async fn authenticate_signer(
+        &self,
+        username: String,
+        key: &SshPublicKey,
+        signer: SignCallback,
+        rsa_hash: Option<RsaSignatureHash>,
+    ) -> napi::Result<bool> {
+        let key = public_key_with_rsa_hash(key.inner.clone(), rsa_hash);
+        let mut handle = self.handle.lock().await;
+        let (_, result) = handle
+            .authenticate_future(username, key, JsSigner::new(signer))
+            .await;
+        Ok(result?)
     }
 
     #[napi]
@@ -438,112 +674,84 @@ impl SshClient {
         Ok(SftpChannel::new(id.into(), sftp))
     }
 
+    /// Sends SSH_MSG_DISCONNECT, by default with `BY_APPLICATION` and an
+    /// empty description.
     #[napi]
-    pub async fn disconnect(&self) -> napi::Result<()> {
+    pub async fn disconnect(
+        &self,
+        reason: Option<DisconnectReasonCode>,
+        description: Option<String>,
+    ) -> napi::Result<()> {
+        let reason = reason.unwrap_or(DisconnectReasonCode::ByApplication);
+        let description = description.unwrap_or_default();
+        self.local_disconnect.set(reason, description.clone());
+
         let handle = self.handle.lock().await;
         handle
-            .disconnect(russh::Disconnect::ByApplication, "", "")
+            .disconnect(reason.into(), &description, "")
             .await
             .map_err(WrappedError::from)?;
         Ok(())
     }
 }
 
-#[napi]
-pub async fn connect(
+#[napi(
+    ts_args_type = "transport: SshTransport, options: ConnectOptions | undefined | null, handler: ClientHandlerCallbacks",
+    ts_return_type = "Promise<SshClient>"
+)]
+pub fn connect(
+    env: Env,
     transport: &SshTransport,
-    cipher_algos: Option<Vec<String>>,
-    kex_algos: Option<Vec<String>>,
-    key_algos: Option<Vec<String>>,
-    mac_algos: Option<Vec<String>>,
-    compression_algos: Option<Vec<String>>,
-    connection_timeout_seconds: Option<u32>,
-    keepalive_interval_seconds: Option<u32>,
-    keepalive_max: u32,
-    server_key_callback: ThreadsafeFunction<SshPublicKey, Promise<bool>>,
-    data_callback: ThreadsafeFunction<(u32, Uint8Array)>,
-    extended_data_callback: ThreadsafeFunction<(u32, u32, Uint8Array)>,
-    eof_callback: ThreadsafeFunction<u32>,
-    close_callback: ThreadsafeFunction<u32>,
-    disconnect_callback: ThreadsafeFunction<Option<napi::Error>>,
-    x11_channel_open_callback: ThreadsafeFunction<(SshChannel, String, u32)>,
-    tcpip_channel_open_callback: ThreadsafeFunction<(SshChannel, String, u32, String, u32)>,
-    agent_channel_open_callback: ThreadsafeFunction<SshChannel>,
-    banner_callback: ThreadsafeFunction<String>,
-) -> napi::Result<SshClient> {
+    options: Option<Object>,
+    handler: Object,
+) -> napi::Result<JsObject> {
+    let options = ConnectOptions::from_object(&env, options)?;
+    let local_disconnect = LocalDisconnect::default();
     let handler = SSHClientHandler {
-        server_key_callback,
-        data_callback,
-        extended_data_callback,
-        eof_callback,
-        close_callback,
-        disconnect_callback,
-        x11_channel_open_callback,
-        tcpip_channel_open_callback,
-        agent_channel_open_callback,
-        banner_callback,
-    };
-
-    let mut preferred = russh::Preferred::DEFAULT.clone();
-    if let Some(cipher_algos) = cipher_algos {
-        preferred.cipher = cipher_algos
-            .into_iter()
-            .filter_map(|x| russh::cipher::Name::try_from(&x[..]).ok())
-            .collect();
-    }
-    if let Some(kex_algos) = kex_algos {
-        preferred.kex = kex_algos
-            .into_iter()
-            .filter_map(|x| russh::kex::Name::try_from(&x[..]).ok())
-            .collect();
-    }
-    if let Some(key_algos) = key_algos {
-        preferred.key = key_algos
-            .into_iter()
-            .filter_map(|x| russh_keys::key::Name::try_from(&x[..]).ok())
-            .collect();
-    }
-    if let Some(mac_algos) = mac_algos {
-        preferred.mac = mac_algos
-            .into_iter()
-            .filter_map(|x| russh::mac::Name::try_from(&x[..]).ok())
-            .collect();
-    }
-    if let Some(compression_algos) = compression_algos {
-        preferred.compression = compression_algos
-            .into_iter()
-            .filter_map(|x| russh::compression::Name::try_from(&x[..]).ok())
-            .collect();
-    }
-
-    let cfg = russh::client::Config {
-        preferred,
-        keepalive_interval: keepalive_interval_seconds.map(|x| Duration::from_secs(x as u64)),
-        keepalive_max: keepalive_max as usize,
-        ..Default::default()
-    };
-
-    let Some(transport) = transport.take().await else {
-        return Err(napi::Error::new(
-            napi::Status::GenericFailure,
-            "Transport already used",
-        ));
-    };
-
-    let connection_fut = russh::client::connect_stream(Arc::new(cfg), transport, handler);
-    let handle = if let Some(connection_timeout_seconds) = connection_timeout_seconds {
-        tokio::time::timeout(
-            Duration::from_secs(connection_timeout_seconds as u64),
-            connection_fut,
-        )
-        .await
-        .map_err(|_| napi::Error::new(napi::Status::GenericFailure, "Connection timeout"))?
-        .map_err(WrappedError::from)?
-    } else {
-        connection_fut.await.map_err(WrappedError::from)?
+        callbacks: ClientHandlerCallbacks::from_object(&env, handler)?,
+        local_disconnect: local_disconnect.clone(),
     };
+    let cfg = options.to_russh_config()?;
+    let transport = transport.clone();
+
+    env.spawn_future(async move {
+        let Some(transport) = transport.take().await else {
+            return Err(ErrorDetails::new(
+                "ERR_TRANSPORT_USED",
+                ErrorCategory::Transport,
+                "Transport already used",
+            )
+            .into());
+        };
+        if let Some(nodelay) = options.nodelay {
+            transport.set_nodelay(nodelay)?;
+        }
 
-    Ok(SshClient {
-        handle: Arc::new(Mutex::new(handle)),
+        let handshake = HandshakeRecorder::default();
+        let transport = RecordingStream::new(transport, handshake.clone());
+        let connection_fut = russh::client::connect_stream(Arc::new(cfg), transport, handler);
+        let handle = if let Some(connection_timeout_seconds) = options.connection_timeout_seconds {
+            tokio::time::timeout(
+                Duration::from_secs(connection_timeout_seconds as u64),
+                connection_fut,
+            )
+            .await
+            .map_err(|_| {
+                ErrorDetails::new(
+                    "ERR_TIMEOUT",
+                    ErrorCategory::Transport,
+                    "Connection timeout",
+                )
+            })??
+        } else {
+            connection_fut.await?
+        };
+
+        Ok(SshClient {
+            handle: Arc::new(Mutex::new(handle)),
+            handshake,
+            local_disconnect,
+            keyboard_interactive: Default::default(),
+        })
     })
 }
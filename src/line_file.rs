@@ -0,0 +1,62 @@
+/// A line of the file, kept verbatim so that saving doesn't reformat it.
+pub(crate) struct Line<T> {
+    pub(crate) text: String,
+    pub(crate) entry: Option<T>,
+}
+
+/// A line-based file such as `known_hosts`, where only some lines hold
+/// entries and the rest must be written back untouched.
+pub(crate) struct LineFile<T> {
+    pub(crate) lines: Vec<Line<T>>,
+    trailing_newline: bool,
+    parse_entry: fn(&str) -> Option<T>,
+}
+
+impl<T> LineFile<T> {
+    pub(crate) fn parse(content: &str, parse_entry: fn(&str) -> Option<T>) -> Self {
+        let trailing_newline = content.ends_with('\n');
+        let content = content.strip_suffix('\n').unwrap_or(content);
+        let mut file = Self {
+            lines: vec![],
+            trailing_newline,
+            parse_entry,
+        };
+        if !content.is_empty() || trailing_newline {
+            file.lines = content.split('\n').map(|x| file.line(x)).collect();
+        }
+        file
+    }
+
+    fn line(&self, text: &str) -> Line<T> {
+        Line {
+            text: text.to_string(),
+            entry: (self.parse_entry)(text),
+        }
+    }
+
+    pub(crate) fn entries(&self) -> impl Iterator<Item = &T> {
+        self.lines.iter().filter_map(|x| x.entry.as_ref())
+    }
+
+    /// Appends `text` as the last line, ending the file with a newline.
+    pub(crate) fn push(&mut self, text: &str) {
+        let line = self.line(text);
+        self.lines.push(line);
+        self.trailing_newline = true;
+    }
+}
+
+impl<T> std::fmt::Display for LineFile<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            f.write_str(&line.text)?;
+        }
+        if self.trailing_newline {
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
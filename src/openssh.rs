@@ -0,0 +1,138 @@
+use std::convert::TryFrom;
+
+use data_encoding::BASE64;
+use ssh_encoding::{Decode, Reader};
+use ssh_key::private::KeypairData;
+use ssh_key::public::KeyData;
+use ssh_key::{Cipher, Kdf, PrivateKey};
+use zeroize::Zeroizing;
+
+use crate::certificate::ssh_key_error;
+use crate::error::{ErrorCategory, ErrorDetails, WrappedError};
+use crate::key::{ecdsa_keypair, wrong_passphrase};
+
+const LABEL: &str = "OPENSSH PRIVATE KEY";
+const AUTH_MAGIC: &[u8] = b"openssh-key-v1\0";
+
+fn invalid(message: &str) -> WrappedError {
+    ErrorDetails::new("ERR_INVALID_KEY", ErrorCategory::Key, message).into()
+}
+
+fn encoding_error<E>(_: E) -> WrappedError {
+    invalid("Invalid OpenSSH key")
+}
+
+pub(crate) fn is_openssh(data: &str) -> bool {
+    data.trim_start()
+        .starts_with(&format!("-----BEGIN {LABEL}-----"))
+}
+
+/// An `openssh-key-v1` key with the private section still encrypted.
+///
+/// Read here rather than by ssh_key, which refuses ECDSA scalars that are
+/// shorter than the curve size. OpenSSH writes them as mpints, so one key
+/// in 256 (one in 4 for P-521) has one.
+pub(crate) struct OpensshFile {
+    pub(crate) cipher: Cipher,
+    pub(crate) kdf: Kdf,
+    pub(crate) public: KeyData,
+    private: Zeroizing<Vec<u8>>,
+    tag: Option<[u8; 16]>,
+}
+
+impl OpensshFile {
+    pub(crate) fn parse(data: &str) -> Result<Self, WrappedError> {
+        // OpenSSH wraps lines at 70 columns, which PEM decoders refuse
+        let body = data
+            .trim()
+            .strip_prefix(&format!("-----BEGIN {LABEL}-----"))
+            .and_then(|x| x.strip_suffix(&format!("-----END {LABEL}-----")))
+            .ok_or_else(|| invalid("Invalid OpenSSH key"))?;
+        let body = Zeroizing::new(body.split_whitespace().collect::<String>());
+        let bytes = Zeroizing::new(BASE64.decode(body.as_bytes()).map_err(encoding_error)?);
+        let reader = &mut bytes
+            .strip_prefix(AUTH_MAGIC)
+            .ok_or_else(|| invalid("Invalid OpenSSH key"))?;
+
+        let cipher =
+            Cipher::decode(reader).map_err(|_| invalid("Unsupported OpenSSH key cipher"))?;
+        let kdf = Kdf::decode(reader).map_err(|_| invalid("Unsupported OpenSSH key derivation"))?;
+        if u32::decode(reader).map_err(encoding_error)? != 1 {
+            return Err(invalid(
+                "OpenSSH key files with several keys aren't supported",
+            ));
+        }
+        let public = reader
+            .read_prefixed(KeyData::decode)
+            .map_err(ssh_key_error)?;
+        let private = Zeroizing::new(Vec::<u8>::decode(reader).map_err(encoding_error)?);
+        let tag = if cipher.has_tag() {
+            let mut tag = [0; 16];
+            reader.read(&mut tag).map_err(encoding_error)?;
+            Some(tag)
+        } else {
+            None
+        };
+        Ok(Self {
+            cipher,
+            kdf,
+            public,
+            private,
+            tag,
+        })
+    }
+
+    pub(crate) fn is_encrypted(&self) -> bool {
+        !self.cipher.is_none()
+    }
+
+    pub(crate) fn decode(&self, passphrase: Option<&str>) -> Result<PrivateKey, WrappedError> {
+        let mut private = self.private.clone();
+        if self.is_encrypted() {
+            let passphrase = passphrase.ok_or(russh_keys::Error::KeyIsEncrypted)?;
+            let (key, iv) = self
+                .kdf
+                .derive_key_and_iv(self.cipher, passphrase)
+                .map_err(ssh_key_error)?;
+            self.cipher
+                .decrypt(&key, &iv, &mut private, self.tag)
+                .map_err(|_| wrong_passphrase("Wrong passphrase for OpenSSH key"))?;
+        }
+
+        let reader = &mut &private[..];
+        let checkint = u32::decode(reader).map_err(encoding_error)?;
+        if u32::decode(reader).map_err(encoding_error)? != checkint {
+            return Err(if self.is_encrypted() {
+                wrong_passphrase("Wrong passphrase for OpenSSH key")
+            } else {
+                invalid("Invalid OpenSSH key")
+            });
+        }
+        let key_data = decode_key_data(reader)?;
+        if KeyData::try_from(&key_data).ok().as_ref() != Some(&self.public) {
+            return Err(invalid("OpenSSH key public and private parts don't match"));
+        }
+        let comment = String::decode(reader).map_err(encoding_error)?;
+        PrivateKey::new(key_data, comment).map_err(ssh_key_error)
+    }
+}
+
+fn decode_key_data(reader: &mut &[u8]) -> Result<KeypairData, WrappedError> {
+    let mut rest = *reader;
+    let algorithm = String::decode(&mut rest).map_err(encoding_error)?;
+    if !algorithm.starts_with("ecdsa-sha2-") {
+        return KeypairData::decode(reader).map_err(ssh_key_error);
+    }
+
+    // The curve and point, followed by the scalar
+    let public = rest;
+    for _ in 0..2 {
+        Vec::<u8>::decode(&mut rest).map_err(encoding_error)?;
+    }
+    let public = &public[..public.len() - rest.len()];
+    let scalar = Zeroizing::new(Vec::<u8>::decode(&mut rest).map_err(encoding_error)?);
+    *reader = rest;
+    Ok(KeypairData::Ecdsa(
+        ecdsa_keypair(public, &scalar).map_err(ssh_key_error)?,
+    ))
+}
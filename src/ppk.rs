@@ -0,0 +1,492 @@
+use std::convert::TryInto;
+
+use aes::Aes256;
+use argon2::{Argon2, Params, Version};
+use cbc::cipher::block_padding::NoPadding;
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use data_encoding::{BASE64, HEXLOWER_PERMISSIVE};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use ssh_encoding::{Decode, Encode};
+use ssh_key::private::{Ed25519Keypair, KeypairData, RsaKeypair, RsaPrivateKey as RsaPrivate};
+use ssh_key::public::RsaPublicKey;
+use ssh_key::{Mpint, PrivateKey};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::certificate::ssh_key_error;
+use crate::error::{ErrorCategory, ErrorDetails, WrappedError};
+use crate::key::{ecdsa_keypair, wrong_passphrase};
+
+const HEADER_PREFIX: &str = "PuTTY-User-Key-File-";
+const ENCRYPTION: &str = "aes256-cbc";
+/// puttygen's defaults, except for the passes, which it calibrates to the
+/// machine.
+const ARGON2_MEMORY: u32 = 8192;
+const ARGON2_PASSES: u32 = 21;
+const ARGON2_PARALLELISM: u32 = 1;
+const HMAC_ACCEPTS_ANY_KEY: &str = "HMAC accepts keys of any length";
+
+pub(crate) fn is_ppk(data: &str) -> bool {
+    data.trim_start().starts_with(HEADER_PREFIX)
+}
+
+fn invalid(message: &str) -> WrappedError {
+    ErrorDetails::new("ERR_INVALID_KEY", ErrorCategory::Key, message).into()
+}
+
+fn encoding_error(err: ssh_encoding::Error) -> WrappedError {
+    ssh_key_error(err.into())
+}
+
+/// Cipher key, IV and MAC key for the private blob.
+struct Keys {
+    cipher: [u8; 32],
+    iv: [u8; 16],
+    mac: Vec<u8>,
+}
+
+impl Drop for Keys {
+    fn drop(&mut self) {
+        self.cipher.zeroize();
+        self.iv.zeroize();
+        self.mac.zeroize();
+    }
+}
+
+fn v2_keys(passphrase: &str) -> Keys {
+    let mut cipher = [0; 32];
+    let first = Sha1::new()
+        .chain_update([0, 0, 0, 0])
+        .chain_update(passphrase)
+        .finalize();
+    let second = Sha1::new()
+        .chain_update([0, 0, 0, 1])
+        .chain_update(passphrase)
+        .finalize();
+    cipher[..20].copy_from_slice(&first);
+    cipher[20..].copy_from_slice(&second[..12]);
+    let mac = Sha1::new()
+        .chain_update("putty-private-key-file-mac-key")
+        .chain_update(passphrase)
+        .finalize()
+        .to_vec();
+    Keys {
+        cipher,
+        iv: [0; 16],
+        mac,
+    }
+}
+
+struct Argon2Kdf {
+    algorithm: argon2::Algorithm,
+    memory: u32,
+    passes: u32,
+    parallelism: u32,
+    salt: Vec<u8>,
+}
+
+impl Argon2Kdf {
+    fn keys(&self, passphrase: &str) -> Result<Keys, WrappedError> {
+        let params = Params::new(self.memory, self.passes, self.parallelism, Some(80))
+            .map_err(|_| invalid("Invalid Argon2 parameters"))?;
+        let mut output = Zeroizing::new([0; 80]);
+        Argon2::new(self.algorithm, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &self.salt, &mut *output)
+            .map_err(|_| invalid("Invalid Argon2 parameters"))?;
+        Ok(Keys {
+            cipher: output[..32].try_into().unwrap_or_default(),
+            iv: output[32..48].try_into().unwrap_or_default(),
+            mac: output[48..].to_vec(),
+        })
+    }
+}
+
+fn mac_data(
+    algorithm: &str,
+    encryption: &str,
+    comment: &str,
+    public: &[u8],
+    private: &[u8],
+) -> Zeroizing<Vec<u8>> {
+    let mut data = Zeroizing::new(vec![]);
+    for field in [
+        algorithm.as_bytes(),
+        encryption.as_bytes(),
+        comment.as_bytes(),
+        public,
+        private,
+    ] {
+        data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        data.extend_from_slice(field);
+    }
+    data
+}
+
+/// `Key: value` headers followed by `*-Lines: n` base64 blocks.
+struct Fields<'a> {
+    lines: std::str::Lines<'a>,
+}
+
+/// Drops the one space PuTTY writes after a header's colon. The rest is
+/// kept as written, since the MAC covers it.
+fn header_value(value: &str) -> &str {
+    value.strip_prefix(' ').unwrap_or(value)
+}
+
+impl<'a> Fields<'a> {
+    fn next(&mut self, name: &str) -> Result<&'a str, WrappedError> {
+        self.lines
+            .next()
+            .and_then(|x| x.strip_prefix(name))
+            .and_then(|x| x.strip_prefix(':'))
+            .map(header_value)
+            .ok_or_else(|| invalid(&format!("Expected `{name}` in PuTTY key")))
+    }
+
+    fn number(&mut self, name: &str) -> Result<u32, WrappedError> {
+        self.next(name)?
+            .parse()
+            .map_err(|_| invalid(&format!("Invalid `{name}` in PuTTY key")))
+    }
+
+    fn blob(&mut self, name: &str) -> Result<Vec<u8>, WrappedError> {
+        let count = self.number(name)?;
+        let mut base64 = String::new();
+        for _ in 0..count {
+            base64.push_str(self.lines.next().unwrap_or_default().trim());
+        }
+        BASE64
+            .decode(base64.as_bytes())
+            .map_err(|_| invalid("Invalid base64 in PuTTY key"))
+    }
+}
+
+/// A PuTTY key file with the private blob still encrypted.
+struct PpkFile<'a> {
+    version: u32,
+    algorithm: &'a str,
+    encryption: &'a str,
+    comment: &'a str,
+    public: Vec<u8>,
+    kdf: Option<Argon2Kdf>,
+    private: Vec<u8>,
+    mac: Vec<u8>,
+}
+
+impl<'a> PpkFile<'a> {
+    fn parse(data: &'a str) -> Result<Self, WrappedError> {
+        let mut fields = Fields {
+            lines: data.trim_start().lines(),
+        };
+        let header = fields.lines.next().unwrap_or_default();
+        let (version, algorithm) = header
+            .strip_prefix(HEADER_PREFIX)
+            .and_then(|x| x.split_once(':'))
+            .map(|(version, algorithm)| (version, header_value(algorithm)))
+            .ok_or_else(|| invalid("Invalid PuTTY key header"))?;
+        let version = match version {
+            "2" => 2,
+            "3" => 3,
+            _ => {
+                return Err(ErrorDetails::new(
+                    "ERR_UNSUPPORTED_KEY_TYPE",
+                    ErrorCategory::Key,
+                    format!("Unsupported PuTTY key version {version}"),
+                )
+                .into())
+            }
+        };
+        let encryption = fields.next("Encryption")?;
+        if encryption != "none" && encryption != ENCRYPTION {
+            return Err(invalid("Unsupported PuTTY key encryption"));
+        }
+        let comment = fields.next("Comment")?;
+        let public = fields.blob("Public-Lines")?;
+        let kdf = if version == 3 && encryption == ENCRYPTION {
+            let algorithm = match fields.next("Key-Derivation")? {
+                "Argon2id" => argon2::Algorithm::Argon2id,
+                "Argon2i" => argon2::Algorithm::Argon2i,
+                "Argon2d" => argon2::Algorithm::Argon2d,
+                _ => return Err(invalid("Unsupported PuTTY key derivation")),
+            };
+            Some(Argon2Kdf {
+                algorithm,
+                memory: fields.number("Argon2-Memory")?,
+                passes: fields.number("Argon2-Passes")?,
+                parallelism: fields.number("Argon2-Parallelism")?,
+                salt: HEXLOWER_PERMISSIVE
+                    .decode(fields.next("Argon2-Salt")?.as_bytes())
+                    .map_err(|_| invalid("Invalid Argon2 salt"))?,
+            })
+        } else {
+            None
+        };
+        let private = fields.blob("Private-Lines")?;
+        let mac = HEXLOWER_PERMISSIVE
+            .decode(fields.next("Private-MAC")?.as_bytes())
+            .map_err(|_| invalid("Invalid PuTTY key MAC"))?;
+        Ok(Self {
+            version,
+            algorithm,
+            encryption,
+            comment,
+            public,
+            kdf,
+            private,
+            mac,
+        })
+    }
+}
+
+/// What can be read from a PuTTY key without the passphrase.
+pub(crate) struct PpkInfo {
+    pub(crate) comment: String,
+    pub(crate) public: Vec<u8>,
+    pub(crate) encrypted: bool,
+    /// Key derivation and its passes.
+    pub(crate) kdf: Option<(&'static str, Option<u32>)>,
+}
+
+pub(crate) fn ppk_info(data: &str) -> Result<PpkInfo, WrappedError> {
+    let file = PpkFile::parse(data)?;
+    let encrypted = file.encryption == ENCRYPTION;
+    let kdf = match &file.kdf {
+        Some(kdf) => Some((
+            match kdf.algorithm {
+                argon2::Algorithm::Argon2d => "argon2d",
+                argon2::Algorithm::Argon2i => "argon2i",
+                argon2::Algorithm::Argon2id => "argon2id",
+            },
+            Some(kdf.passes),
+        )),
+        // Version 2 derives the key with two rounds of SHA-1
+        None if encrypted => Some(("sha1", None)),
+        None => None,
+    };
+    Ok(PpkInfo {
+        comment: file.comment.to_string(),
+        public: file.public,
+        encrypted,
+        kdf,
+    })
+}
+
+pub(crate) fn decode_ppk(data: &str, passphrase: Option<&str>) -> Result<PrivateKey, WrappedError> {
+    let PpkFile {
+        version,
+        algorithm,
+        encryption,
+        comment,
+        public,
+        kdf,
+        private,
+        mac: expected_mac,
+    } = PpkFile::parse(data)?;
+    let mut private = Zeroizing::new(private);
+    let encrypted = encryption == ENCRYPTION;
+
+    let passphrase = match (encrypted, passphrase) {
+        (false, _) => "",
+        (true, Some(passphrase)) => passphrase,
+        (true, None) => {
+            return Err(russh_keys::Error::KeyIsEncrypted.into());
+        }
+    };
+    let keys = match &kdf {
+        Some(kdf) => kdf.keys(passphrase)?,
+        None if version == 2 => v2_keys(passphrase),
+        // Unencrypted v3 keys are MACed with an empty key
+        None => Keys {
+            cipher: [0; 32],
+            iv: [0; 16],
+            mac: vec![],
+        },
+    };
+
+    // The MAC covers the decrypted blob, padding included
+    if encrypted {
+        cbc::Decryptor::<Aes256>::new(&keys.cipher.into(), &keys.iv.into())
+            .decrypt_padded_mut::<NoPadding>(&mut private[..])
+            .map_err(|_| invalid("Invalid PuTTY key padding"))?;
+    }
+
+    let mac_data = mac_data(algorithm, encryption, comment, &public, &private);
+    let mac_ok = if version == 2 {
+        let mut mac = Hmac::<Sha1>::new_from_slice(&keys.mac).expect(HMAC_ACCEPTS_ANY_KEY);
+        mac.update(&mac_data);
+        mac.verify_slice(&expected_mac).is_ok()
+    } else {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&keys.mac).expect(HMAC_ACCEPTS_ANY_KEY);
+        mac.update(&mac_data);
+        mac.verify_slice(&expected_mac).is_ok()
+    };
+    if !mac_ok {
+        return Err(if encrypted {
+            wrong_passphrase("Wrong passphrase for PuTTY key")
+        } else {
+            invalid("PuTTY key MAC mismatch, the file is corrupt")
+        });
+    }
+
+    let key_data = decode_key_data(&public, &private)?;
+    PrivateKey::new(key_data, comment).map_err(ssh_key_error)
+}
+
+fn decode_key_data(public: &[u8], private: &[u8]) -> Result<KeypairData, WrappedError> {
+    let public = &mut &public[..];
+    let private = &mut &private[..];
+    let algorithm = String::decode(public).map_err(encoding_error)?;
+    Ok(match algorithm.as_str() {
+        "ssh-rsa" => {
+            let e = Mpint::decode(public).map_err(ssh_key_error)?;
+            let n = Mpint::decode(public).map_err(ssh_key_error)?;
+            let d = Mpint::decode(private).map_err(ssh_key_error)?;
+            let p = Mpint::decode(private).map_err(ssh_key_error)?;
+            let q = Mpint::decode(private).map_err(ssh_key_error)?;
+            let iqmp = Mpint::decode(private).map_err(ssh_key_error)?;
+            KeypairData::Rsa(RsaKeypair {
+                public: RsaPublicKey { e, n },
+                private: RsaPrivate { d, iqmp, p, q },
+            })
+        }
+        "ssh-ed25519" => {
+            let public_key = Vec::<u8>::decode(public).map_err(encoding_error)?;
+            let seed = Zeroizing::new(Vec::<u8>::decode(private).map_err(encoding_error)?);
+            let seed: Zeroizing<[u8; 32]> = seed
+                .as_slice()
+                .try_into()
+                .map(Zeroizing::new)
+                .map_err(|_| invalid("Invalid Ed25519 key in PuTTY key"))?;
+            let keypair = Ed25519Keypair::from_seed(&seed);
+            if keypair.public.as_ref() != public_key.as_slice() {
+                return Err(invalid("PuTTY key public and private parts don't match"));
+            }
+            KeypairData::Ed25519(keypair)
+        }
+        x if x.starts_with("ecdsa-sha2-") => {
+            // The rest of the public blob is the curve and point
+            let scalar = Zeroizing::new(Vec::<u8>::decode(private).map_err(encoding_error)?);
+            KeypairData::Ecdsa(ecdsa_keypair(public, &scalar).map_err(ssh_key_error)?)
+        }
+        _ => {
+            return Err(ErrorDetails::new(
+                "ERR_UNSUPPORTED_KEY_TYPE",
+                ErrorCategory::Key,
+                format!("Unsupported PuTTY key type {algorithm}"),
+            )
+            .into())
+        }
+    })
+}
+
+fn encode_private_blob(key: &KeypairData) -> Result<Zeroizing<Vec<u8>>, WrappedError> {
+    let mut blob = Zeroizing::new(vec![]);
+    match key {
+        KeypairData::Rsa(key) => {
+            for x in [
+                &key.private.d,
+                &key.private.p,
+                &key.private.q,
+                &key.private.iqmp,
+            ] {
+                x.encode(&mut *blob).map_err(encoding_error)?;
+            }
+        }
+        KeypairData::Ed25519(key) => Zeroizing::new(key.private.to_bytes())
+            .encode(&mut *blob)
+            .map_err(encoding_error)?,
+        KeypairData::Ecdsa(key) => Mpint::from_positive_bytes(key.private_key_bytes())
+            .map_err(ssh_key_error)?
+            .encode(&mut *blob)
+            .map_err(encoding_error)?,
+        _ => return Err(invalid("Unsupported key type for PuTTY export")),
+    }
+    Ok(blob)
+}
+
+fn base64_lines(data: &[u8]) -> (usize, String) {
+    let base64 = BASE64.encode(data);
+    let lines = base64
+        .as_bytes()
+        .chunks(64)
+        .map(|x| String::from_utf8_lossy(x).into_owned())
+        .collect::<Vec<_>>();
+    (lines.len(), lines.join("\n"))
+}
+
+/// Writes a version 3 file, encrypted with Argon2id and AES-256-CBC when
+/// a passphrase is given.
+pub(crate) fn encode_ppk(
+    key: &PrivateKey,
+    passphrase: Option<&str>,
+    passes: Option<u32>,
+) -> Result<String, WrappedError> {
+    let algorithm = key.algorithm().to_string();
+    let comment = key.comment();
+    let public = key.public_key().to_bytes().map_err(ssh_key_error)?;
+    let mut private = encode_private_blob(key.key_data())?;
+
+    let mut rng = rand::rngs::OsRng;
+    let (encryption, kdf) = match passphrase {
+        Some(_) => {
+            let mut salt = vec![0; 16];
+            rng.fill_bytes(&mut salt);
+            let kdf = Argon2Kdf {
+                algorithm: argon2::Algorithm::Argon2id,
+                memory: ARGON2_MEMORY,
+                passes: passes.unwrap_or(ARGON2_PASSES),
+                parallelism: ARGON2_PARALLELISM,
+                salt,
+            };
+            (ENCRYPTION, Some(kdf))
+        }
+        None => ("none", None),
+    };
+    let keys = match (&kdf, passphrase) {
+        (Some(kdf), Some(passphrase)) => Some(kdf.keys(passphrase)?),
+        _ => None,
+    };
+
+    if keys.is_some() {
+        let mut padding = vec![0; 15 - (private.len() + 15) % 16];
+        rng.fill_bytes(&mut padding);
+        private.extend_from_slice(&padding);
+    }
+
+    // The MAC covers the plaintext blob, so it's computed before encrypting
+    let mac_key = keys.as_ref().map_or(&[][..], |x| &x.mac);
+    let mut mac = Hmac::<Sha256>::new_from_slice(mac_key).expect(HMAC_ACCEPTS_ANY_KEY);
+    mac.update(&mac_data(
+        &algorithm, encryption, comment, &public, &private,
+    ));
+
+    if let Some(keys) = &keys {
+        let len = private.len();
+        cbc::Encryptor::<Aes256>::new(&keys.cipher.into(), &keys.iv.into())
+            .encrypt_padded_mut::<NoPadding>(&mut private[..], len)
+            .map_err(|_| invalid("Invalid PuTTY key padding"))?;
+    }
+
+    let mut out =
+        format!("{HEADER_PREFIX}3: {algorithm}\nEncryption: {encryption}\nComment: {comment}\n");
+    let (count, lines) = base64_lines(&public);
+    out.push_str(&format!("Public-Lines: {count}\n{lines}\n"));
+    if let Some(kdf) = &kdf {
+        out.push_str(&format!(
+            "Key-Derivation: Argon2id\nArgon2-Memory: {}\nArgon2-Passes: {}\nArgon2-Parallelism: {}\nArgon2-Salt: {}\n",
+            kdf.memory,
+            kdf.passes,
+            kdf.parallelism,
+            HEXLOWER_PERMISSIVE.encode(&kdf.salt)
+        ));
+    }
+    let (count, lines) = base64_lines(&private);
+    out.push_str(&format!("Private-Lines: {count}\n{lines}\n"));
+    out.push_str(&format!(
+        "Private-MAC: {}\n",
+        HEXLOWER_PERMISSIVE.encode(&mac.finalize().into_bytes())
+    ));
+    Ok(out)
+}
@@ -0,0 +1,40 @@
+use napi::bindgen_prelude::{Either, Uint8Array};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::error::{ErrorCategory, ErrorDetails};
+
+/// A password, passphrase or other secret. A `Uint8Array` holds UTF-8 and
+/// is wiped once read; JS strings are immutable and can't be cleared, so
+/// prefer arrays for anything sensitive.
+pub type Secret = Either<String, Uint8Array>;
+
+/// Moves `secret` into memory that is wiped on drop, zero-filling the
+/// caller's `Uint8Array`.
+pub(crate) fn take_secret(secret: Secret) -> napi::Result<Zeroizing<String>> {
+    let bytes = match secret {
+        Either::A(string) => return Ok(Zeroizing::new(string)),
+        Either::B(mut array) => {
+            let bytes = array.to_vec();
+            array.zeroize();
+            bytes
+        }
+    };
+    match String::from_utf8(bytes) {
+        Ok(string) => Ok(Zeroizing::new(string)),
+        Err(e) => {
+            e.into_bytes().zeroize();
+            Err(ErrorDetails::new(
+                "ERR_INVALID_ARGUMENT",
+                ErrorCategory::Argument,
+                "Secrets must be valid UTF-8",
+            )
+            .into())
+        }
+    }
+}
+
+pub(crate) fn take_optional_secret(
+    secret: Option<Secret>,
+) -> napi::Result<Option<Zeroizing<String>>> {
+    secret.map(take_secret).transpose()
+}
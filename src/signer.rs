@@ -0,0 +1,51 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use napi::bindgen_prelude::{Promise, Uint8Array};
+use napi::threadsafe_function::ThreadsafeFunction;
+use russh::CryptoVec;
+use russh_keys::encoding::Encoding;
+use russh_keys::key::PublicKey;
+
+use crate::error::WrappedError;
+
+/// Called with the data to sign and the signature algorithm name; resolves
+/// to the SSH signature blob (`string algorithm, string signature`).
+pub type SignCallback = ThreadsafeFunction<(Uint8Array, String), Promise<Uint8Array>>;
+
+/// Delegates `publickey` auth signatures to a JS callback, the same way
+/// `AgentClient` does for agent identities.
+pub struct JsSigner {
+    callback: SignCallback,
+}
+
+impl JsSigner {
+    pub fn new(callback: SignCallback) -> Self {
+        Self { callback }
+    }
+
+    async fn sign(&self, key: &PublicKey, data: &CryptoVec) -> Result<Vec<u8>, WrappedError> {
+        let signature = self
+            .callback
+            .call_async(Ok((data.to_vec().into(), key.name().to_string())))
+            .await?
+            .await?;
+        Ok(signature.to_vec())
+    }
+}
+
+impl russh::Signer for JsSigner {
+    type Error = WrappedError;
+    type Future = Pin<Box<dyn Future<Output = (Self, Result<CryptoVec, Self::Error>)> + Send>>;
+
+    fn auth_publickey_sign(self, key: &PublicKey, mut to_sign: CryptoVec) -> Self::Future {
+        let key = key.clone();
+        Box::pin(async move {
+            let result = self.sign(&key, &to_sign).await.map(|signature| {
+                to_sign.extend_ssh_string(&signature);
+                to_sign
+            });
+            (self, result)
+        })
+    }
+}
@@ -0,0 +1,195 @@
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use napi::bindgen_prelude::Uint8Array;
+use napi_derive::napi;
+use russh::CryptoVec;
+use ssh_encoding::Decode;
+use ssh_key::{HashAlg, LineEnding, SshSig};
+
+use crate::agent::{get_agent_client, AgentConnection};
+use crate::authorized_keys::parse_entry;
+use crate::certificate::{public_key_from_data, ssh_key_error};
+use crate::error::{ErrorCategory, ErrorDetails, WrappedError};
+use crate::key::{public_key_with_rsa_hash, RsaSignatureHash, SshPublicKey};
+use crate::known_hosts::pattern_list_match;
+
+/// Message digest signed by `ssh-keygen -Y sign`.
+#[napi(string_enum = "lowercase")]
+pub enum SshSigHash {
+    Sha256,
+    /// The default, as in `ssh-keygen`.
+    Sha512,
+}
+
+fn hash_alg(hash: Option<SshSigHash>) -> HashAlg {
+    match hash {
+        Some(SshSigHash::Sha256) => HashAlg::Sha256,
+        Some(SshSigHash::Sha512) | None => HashAlg::Sha512,
+    }
+}
+
+#[napi(object, object_from_js = false)]
+pub struct SshSigVerification {
+    /// Principals of the `allowed_signers` line that matched the key.
+    pub principals: Vec<String>,
+    pub key: SshPublicKey,
+}
+
+fn invalid_signature<S: ToString>(message: S) -> napi::Error {
+    ErrorDetails::new("ERR_SIGNATURE_INVALID", ErrorCategory::Auth, message).into()
+}
+
+pub(crate) fn sign(
+    key: &ssh_key::PrivateKey,
+    namespace: &str,
+    data: &[u8],
+    hash: Option<SshSigHash>,
+) -> napi::Result<String> {
+    key.sign(namespace, hash_alg(hash), data)
+        .and_then(|x| x.to_pem(LineEnding::LF))
+        .map_err(|e| ssh_key_error(e).into())
+}
+
+/// Produces an armored `SSH SIGNATURE` with a key held by an agent, like
+/// `ssh-keygen -Y sign -f key.pub` does when `SSH_AUTH_SOCK` is set.
+#[napi]
+pub async fn sign_sshsig_with_agent(
+    connection: &AgentConnection,
+    key: &SshPublicKey,
+    namespace: String,
+    data: Uint8Array,
+    hash: Option<SshSigHash>,
+) -> napi::Result<String> {
+    let hash_alg = hash_alg(hash);
+    let signed_data = SshSig::signed_data(&namespace, hash_alg, &data).map_err(ssh_key_error)?;
+    // SSHSIG only allows the SHA-2 flavours of RSA signatures
+    let public_key = public_key_with_rsa_hash(key.inner.clone(), Some(RsaSignatureHash::Sha512));
+
+    let agent = get_agent_client(connection).await?;
+    let (_, result) = agent
+        .sign_request(&public_key, CryptoVec::from_slice(&signed_data))
+        .await;
+    let result = result.map_err(WrappedError::from)?;
+    // The agent client appends the signature to the data as an SSH string,
+    // and nothing if the agent answered with another algorithm
+    let signature = result
+        .get(signed_data.len() + 4..)
+        .filter(|x| !x.is_empty())
+        .ok_or_else(|| {
+            napi::Error::from(ErrorDetails::new(
+                "ERR_AGENT_FAILURE",
+                ErrorCategory::Agent,
+                "The agent did not sign the data",
+            ))
+        })?;
+    let signature = ssh_key::Signature::decode(&mut &signature[..]).map_err(invalid_signature)?;
+
+    let key_data = key.key_data()?;
+    let signature =
+        SshSig::new(key_data.clone(), &namespace, hash_alg, signature).map_err(ssh_key_error)?;
+    // Don't hand out signatures the agent got wrong
+    ssh_key::PublicKey::from(key_data)
+        .verify(&namespace, &data, &signature)
+        .map_err(invalid_signature)?;
+    signature
+        .to_pem(LineEnding::LF)
+        .map_err(|e| ssh_key_error(e).into())
+}
+
+/// `YYYYMMDD[HHMM[SS]][Z]`, in local time unless suffixed with `Z`.
+fn parse_time(value: &str) -> Option<i64> {
+    let (value, utc) = match value.strip_suffix(['Z', 'z']) {
+        Some(value) => (value, true),
+        None => (value, false),
+    };
+    let time = match value.len() {
+        8 => NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)?,
+        12 => NaiveDateTime::parse_from_str(value, "%Y%m%d%H%M").ok()?,
+        14 => NaiveDateTime::parse_from_str(value, "%Y%m%d%H%M%S").ok()?,
+        _ => return None,
+    };
+    Some(if utc {
+        Utc.from_utc_datetime(&time).timestamp()
+    } else {
+        Local.from_local_datetime(&time).earliest()?.timestamp()
+    })
+}
+
+/// Splits off the leading principals field, which may be quoted. A closing
+/// quote has to end the field, as in ssh-keygen.
+fn split_principals(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+    if let Some(rest) = line.strip_prefix('"') {
+        let end = rest.find('"')?;
+        let (principals, rest) = (&rest[..end], &rest[end + 1..]);
+        return rest
+            .starts_with(char::is_whitespace)
+            .then_some((principals, rest));
+    }
+    line.split_once(char::is_whitespace)
+}
+
+/// Checks an armored `SSH SIGNATURE` over `data` against an
+/// `allowed_signers` file, like `ssh-keygen -Y verify`, and returns the
+/// principals it belongs to. Lines are honoured for their `namespaces`,
+/// `valid-after` and `valid-before` options; `cert-authority` lines are
+/// skipped, since certificate-backed signatures aren't supported.
+#[napi]
+pub fn verify_sshsig(
+    allowed_signers: String,
+    namespace: String,
+    data: Uint8Array,
+    signature: String,
+) -> napi::Result<SshSigVerification> {
+    let signature = SshSig::from_pem(signature.trim()).map_err(invalid_signature)?;
+    let public_key = ssh_key::PublicKey::from(signature.public_key().clone());
+    public_key
+        .verify(&namespace, &data, &signature)
+        .map_err(|e| invalid_signature(format!("Signature verification failed: {e}")))?;
+
+    let blob = public_key.to_bytes().map_err(invalid_signature)?;
+    let now = Utc::now().timestamp();
+    for line in allowed_signers.lines() {
+        let Some((principals, rest)) = split_principals(line) else {
+            continue;
+        };
+        let Some(entry) = parse_entry(rest) else {
+            continue;
+        };
+        if entry.key != blob
+            || entry
+                .options
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case("cert-authority"))
+        {
+            continue;
+        }
+        if let Some(namespaces) = entry.option_values("namespaces").next() {
+            if !pattern_list_match(&namespaces, &namespace) {
+                continue;
+            }
+        }
+        // Unparseable times reject the line, as in ssh-keygen
+        if let Some(after) = entry.option_values("valid-after").next() {
+            if parse_time(&after).map_or(true, |x| now < x) {
+                continue;
+            }
+        }
+        if let Some(before) = entry.option_values("valid-before").next() {
+            if parse_time(&before).map_or(true, |x| now > x) {
+                continue;
+            }
+        }
+        return Ok(SshSigVerification {
+            principals: principals.split(',').map(Into::into).collect(),
+            key: public_key_from_data(signature.public_key())?,
+        });
+    }
+    Err(ErrorDetails::new(
+        "ERR_SIGNER_NOT_ALLOWED",
+        ErrorCategory::Auth,
+        "No allowed signer matches the signing key",
+    )
+    .into())
+}
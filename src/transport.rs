@@ -3,7 +3,7 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
-use async_http_proxy::http_connect_tokio;
+use async_http_proxy::{http_connect_tokio, HttpError};
 use delegate::delegate;
 use napi_derive::napi;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
@@ -13,8 +13,10 @@ use tokio::sync::Mutex;
 use tokio_socks::tcp::Socks5Stream;
 
 use crate::channel::SshChannel;
+use crate::error::{ErrorCategory, ErrorDetails, WrappedError};
 
 #[napi]
+#[derive(Clone)]
 pub struct SshTransport(Arc<Mutex<Option<SshTransportInner>>>);
 
 pub(crate) enum SshTransportInner {
@@ -31,7 +33,7 @@ impl Drop for SshTransportInner {
                 let _ = futures::executor::block_on(socket.shutdown());
             }
             SshTransportInner::Command(child) => {
-                let _ = child.kill();
+                let _ = child.start_kill();
             }
             SshTransportInner::SshChannel(_) | SshTransportInner::SocksProxy(_) => {
                 // just drop the stream
@@ -44,8 +46,10 @@ impl Drop for SshTransportInner {
 impl SshTransport {
     #[napi]
     pub async fn new_socket(address: String) -> napi::Result<SshTransport> {
-        let socket = tokio::net::TcpStream::connect(address.clone()).await?;
-        socket.set_nodelay(true)?;
+        let socket = tokio::net::TcpStream::connect(address.clone())
+            .await
+            .map_err(WrappedError::from)?;
+        socket.set_nodelay(true).map_err(WrappedError::from)?;
         Ok(Self(Arc::new(Mutex::new(Some(SshTransportInner::Socket(
             socket,
         ))))))
@@ -57,7 +61,8 @@ impl SshTransport {
             .args(args)
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
-            .spawn()?;
+            .spawn()
+            .map_err(WrappedError::from)?;
 
         Ok(Self(Arc::new(Mutex::new(Some(
             SshTransportInner::Command(child),
@@ -67,10 +72,12 @@ impl SshTransport {
     #[napi]
     pub async fn new_ssh_channel(channel: &SshChannel) -> napi::Result<SshTransport> {
         let Some(handle) = channel.take().await else {
-            return Err(napi::Error::new(
-                napi::Status::GenericFailure,
+            return Err(ErrorDetails::new(
+                "ERR_CHANNEL_CONSUMED",
+                ErrorCategory::Channel,
                 "Channel is already consumed",
-            ));
+            )
+            .into());
         };
 
         let stream = handle.into_stream();
@@ -92,7 +99,7 @@ impl SshTransport {
             (target_host, target_port),
         )
         .await
-        .map_err(crate::WrappedError::from)?;
+        .map_err(WrappedError::from)?;
 
         Ok(Self(Arc::new(Mutex::new(Some(
             SshTransportInner::SocksProxy(stream),
@@ -106,11 +113,14 @@ impl SshTransport {
         target_host: String,
         target_port: u16,
     ) -> napi::Result<SshTransport> {
-        let mut socket = tokio::net::TcpStream::connect((proxy_host, proxy_port)).await?;
-        socket.set_nodelay(true)?;
+        // Failing to reach the proxy itself is reported as a proxy error
+        let mut socket = tokio::net::TcpStream::connect((proxy_host, proxy_port))
+            .await
+            .map_err(|e| WrappedError::from(HttpError::from(e)))?;
+        socket.set_nodelay(true).map_err(WrappedError::from)?;
         http_connect_tokio(&mut socket, target_host.as_str(), target_port)
             .await
-            .map_err(crate::WrappedError::from)?;
+            .map_err(WrappedError::from)?;
 
         Ok(Self(Arc::new(Mutex::new(Some(SshTransportInner::Socket(
             socket,
@@ -122,6 +132,16 @@ impl SshTransport {
     }
 }
 
+impl SshTransportInner {
+    pub(crate) fn set_nodelay(&self, nodelay: bool) -> std::io::Result<()> {
+        match self {
+            SshTransportInner::Socket(socket) => socket.set_nodelay(nodelay),
+            SshTransportInner::SocksProxy(stream) => stream.set_nodelay(nodelay),
+            SshTransportInner::Command(_) | SshTransportInner::SshChannel(_) => Ok(()),
+        }
+    }
+}
+
 impl AsyncRead for SshTransportInner {
     delegate! {
         to match self.get_mut() {
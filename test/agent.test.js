@@ -0,0 +1,39 @@
+const { test } = require('node:test')
+const assert = require('node:assert')
+const { spawn, spawnSync } = require('node:child_process')
+const fs = require('node:fs')
+const os = require('node:os')
+const path = require('node:path')
+
+const russh = require('../lib/native')
+
+const noAgent = process.platform === 'win32' || spawnSync('ssh-agent', ['-h']).error !== undefined
+
+test('the agent takes P-521 keys', { skip: noAgent && 'needs OpenSSH ssh-agent' }, async t => {
+    const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'russh-agent-'))
+    const socket = path.join(dir, 'agent.sock')
+    const agent = spawn('ssh-agent', ['-D', '-a', socket], { stdio: 'ignore' })
+    t.after(() => {
+        agent.kill()
+        fs.rmSync(dir, { recursive: true, force: true })
+    })
+    while (!fs.existsSync(socket)) {
+        await new Promise(resolve => setTimeout(resolve, 20))
+    }
+
+    const connection = russh.AgentConnection.new(russh.AgentConnectionKind.Unix, socket)
+    const client = new russh.SshAgentClient(connection)
+    // One key in 4 has a scalar with a leading zero byte
+    for (let i = 0; i < 20; i++) {
+        await client.addIdentity(await russh.generateKey(russh.KeyType.Ecdsa, 521, `key ${i}`))
+    }
+    const identities = await client.listIdentities()
+    assert.deepStrictEqual(identities.map(x => x.comment()), [...Array(20).keys()].map(i => `key ${i}`))
+
+    const data = new Uint8Array([1, 2, 3])
+    for (const key of identities) {
+        const signature = await russh.signSshsigWithAgent(connection, key, 'file', data)
+        const allowedSigners = `me ${key.algorithm()} ${key.base64()}\n`
+        assert.deepStrictEqual(russh.verifySshsig(allowedSigners, 'file', data, signature).principals, ['me'])
+    }
+})
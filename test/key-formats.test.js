@@ -0,0 +1,61 @@
+const { test } = require('node:test')
+const assert = require('node:assert')
+const fs = require('node:fs')
+const path = require('node:path')
+
+const russh = require('../lib/native')
+
+const fixture = name => fs.readFileSync(path.join(__dirname, 'fixtures', name), 'utf8')
+const fingerprint = name => russh.parsePublicKey(fixture(`${name}.pub`)).fingerprint()
+
+// Fixture, the key it holds and whether the format keeps the comment
+const keys = [
+    ['ed25519', 'ed25519', true],
+    ['ed25519-encrypted', 'ed25519', true],
+    ['rsa', 'rsa', true],
+    ['rsa.pem', 'rsa', false],
+    ['rsa-pkcs8.pem', 'rsa', false],
+    ['ecdsa', 'ecdsa', true],
+    ['ecdsa-pkcs8.pem', 'ecdsa', false],
+    ['ecdsa-p256.pem', 'ecdsa-p256', false],
+]
+for (const name of ['ed25519', 'rsa', 'ecdsa']) {
+    for (const version of [2, 3]) {
+        keys.push([`${name}-v${version}.ppk`, name, true])
+        keys.push([`${name}-v${version}-encrypted.ppk`, name, true])
+    }
+}
+
+for (const [file, name, hasComment] of keys) {
+    test(`reads ${file}`, () => {
+        const data = fixture(file)
+        const info = russh.keyInfo(data)
+        const key = russh.parseKey(data, info.encrypted ? 'secret' : undefined)
+        assert.strictEqual(key.publicKey().fingerprint(), fingerprint(name))
+        if (hasComment) {
+            assert.strictEqual(key.comment(), `${name} fixture`)
+        }
+        if (info.encrypted) {
+            assert.throws(() => russh.parseKey(data, 'wrong'), { code: 'ERR_WRONG_PASSPHRASE' })
+        }
+    })
+}
+
+for (const name of ['ed25519', 'rsa', 'ecdsa']) {
+    test(`${name} survives export`, async () => {
+        const key = russh.parseKey(fixture(name))
+        const formats = name === 'ed25519' ? ['openssh', 'pkcs8', 'ppk'] : ['openssh', 'pkcs8', 'pem', 'ppk']
+        for (const format of formats) {
+            for (const passphrase of [undefined, 'secret']) {
+                const exported = await key.export({ format, passphrase, rounds: format === 'ppk' ? 1 : undefined })
+                const back = russh.parseKey(exported, passphrase)
+                assert.strictEqual(back.publicKey().fingerprint(), fingerprint(name), format)
+            }
+        }
+    })
+}
+
+test('PuTTY comments are read as written', () => {
+    assert.strictEqual(russh.parseKey(fixture('ed25519-empty-comment.ppk')).comment(), '')
+    assert.strictEqual(russh.parseKey(fixture('ed25519-trailing-space.ppk')).comment(), 'ed25519 fixture ')
+})
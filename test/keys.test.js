@@ -0,0 +1,31 @@
+const { test } = require('node:test')
+const assert = require('node:assert')
+
+const russh = require('../lib/native')
+
+test('P-521 keys survive every export format', async () => {
+    // One key in 256 has a scalar with a leading zero byte, so try plenty
+    for (let i = 0; i < 100; i++) {
+        const key = await russh.generateKey(russh.KeyType.Ecdsa, 521)
+        const fingerprint = key.publicKey().fingerprint()
+        for (const format of ['openssh', 'pkcs8', 'pem', 'ppk']) {
+            const exported = await key.export({ format })
+            assert.strictEqual(russh.parseKey(exported).publicKey().fingerprint(), fingerprint, format)
+        }
+        await key.signSshsig('file', new Uint8Array([1, 2, 3]))
+    }
+})
+
+test('encrypted PEM export', async () => {
+    for (const [type, bits] of [[russh.KeyType.Rsa, 2048], [russh.KeyType.Ecdsa, 384]]) {
+        const key = await russh.generateKey(type, bits)
+        const exported = await key.export({ format: 'pem', passphrase: 'secret' })
+        assert.match(exported, /^Proc-Type: 4,ENCRYPTED$/m)
+        assert.match(exported, /^DEK-Info: AES-128-CBC,[0-9A-F]{32}$/m)
+        assert.strictEqual(
+            russh.parseKey(exported, 'secret').publicKey().fingerprint(),
+            key.publicKey().fingerprint(),
+        )
+        assert.throws(() => russh.parseKey(exported, 'wrong'), { code: 'ERR_WRONG_PASSPHRASE' })
+    }
+})
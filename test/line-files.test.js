@@ -0,0 +1,65 @@
+const { test } = require('node:test')
+const assert = require('node:assert')
+const fs = require('node:fs')
+const path = require('node:path')
+
+const russh = require('../lib/native')
+
+const publicKey = name => fs.readFileSync(path.join(__dirname, 'fixtures', `${name}.pub`), 'utf8').trim()
+const [ed25519, rsa, ecdsa] = ['ed25519', 'rsa', 'ecdsa'].map(publicKey)
+const keyPart = line => line.split(' ').slice(0, 2).join(' ')
+
+const knownHosts = [
+    '# Managed by hand',
+    '',
+    `example.com,192.0.2.1 ${ed25519}`,
+    `[example.com]:2222 ${keyPart(rsa)}`,
+    `|1|c2FsdHNhbHRzYWx0c2FsdHNhbHQ=|aGFzaGhhc2hoYXNoaGFzaGhhc2g= ${keyPart(ecdsa)}`,
+    `@cert-authority *.example.com ${keyPart(ed25519)} ca`,
+    `@revoked   old.example.com\t${keyPart(rsa)}`,
+    'not a known_hosts line',
+    `!bad.example.com,*.example.com  ${keyPart(ecdsa)}  trailing  comment  `,
+]
+
+const authorizedKeys = [
+    '# Deploy keys',
+    ed25519,
+    `restrict,command="echo a, b",from="192.0.2.0/24" ${rsa}`,
+    `environment="NAME=some value"\t${keyPart(ecdsa)}`,
+    '',
+    'garbage',
+    `   ${keyPart(ed25519)}   spaced   comment`,
+]
+
+for (const [name, File, lines] of [
+    ['KnownHosts', russh.KnownHosts, knownHosts],
+    ['AuthorizedKeys', russh.AuthorizedKeys, authorizedKeys],
+]) {
+    test(`${name} writes back what it parsed`, () => {
+        for (const content of [
+            lines.join('\n') + '\n',
+            lines.join('\n'),
+            lines.join('\r\n') + '\r\n',
+            '',
+            '\n\n',
+        ]) {
+            assert.strictEqual(File.parse(content).toString(), content)
+        }
+    })
+}
+
+test('KnownHosts keeps existing lines when adding', () => {
+    const content = knownHosts.join('\n')
+    const file = russh.KnownHosts.parse(content)
+    file.add('new.example.com', 22, russh.parsePublicKey(ed25519))
+    const result = file.toString()
+    assert.ok(result.startsWith(content + '\n'))
+    assert.strictEqual(result.split('\n').length, knownHosts.length + 2)
+})
+
+test('AuthorizedKeys keeps existing lines when adding', () => {
+    const content = authorizedKeys.join('\n') + '\n'
+    const file = russh.AuthorizedKeys.parse(content)
+    file.add(russh.parsePublicKey(ecdsa), ['restrict'], 'added')
+    assert.strictEqual(file.toString(), `${content}restrict ${keyPart(ecdsa)} added\n`)
+})
@@ -0,0 +1,32 @@
+const { test } = require('node:test')
+const assert = require('node:assert')
+const fs = require('node:fs')
+const path = require('node:path')
+
+const russh = require('../lib/native')
+
+const fixture = name => fs.readFileSync(path.join(__dirname, 'fixtures', name), 'utf8')
+const data = new Uint8Array([1, 2, 3])
+
+test('allowed_signers principals', async () => {
+    const signature = await russh.parseKey(fixture('ed25519')).signSshsig('file', data)
+    const key = fixture('ed25519.pub').split(' ').slice(0, 2).join(' ')
+    const verify = allowedSigners => russh.verifySshsig(allowedSigners, 'file', data, signature).principals
+
+    assert.deepStrictEqual(verify(`me@example.com,you@example.com ${key}\n`), ['me@example.com', 'you@example.com'])
+    assert.deepStrictEqual(verify(`"me@example.com,you@example.com"\t${key}\n`), ['me@example.com', 'you@example.com'])
+    for (const line of [`"me@example.com",you@example.com ${key}`, `"me@example.com"${key}`, `"me@example.com ${key}`]) {
+        assert.throws(() => verify(line), { code: 'ERR_SIGNER_NOT_ALLOWED' }, line)
+    }
+})
+
+test('P-521 signatures verify', async () => {
+    // Half of them have an r or s shorter than the curve size
+    for (let i = 0; i < 20; i++) {
+        const key = await russh.generateKey(russh.KeyType.Ecdsa, 521)
+        const signature = await key.signSshsig('file', data)
+        const publicKey = key.publicKey()
+        const allowedSigners = `me ${publicKey.algorithm()} ${publicKey.base64()}\n`
+        assert.deepStrictEqual(russh.verifySshsig(allowedSigners, 'file', data, signature).principals, ['me'])
+    }
+})